@@ -243,6 +243,239 @@ async function main() {
         console.log(`Regular array sent: ${arrayResponse.status}`);
         console.log(`Data type header: ${arrayResult.headers['X-Array-Type']}`);
 
+        // Example 17: Cancelling requests with AbortController
+        console.log("\n17. AbortController and AbortSignal:");
+        const controller = new AbortController();
+        controller.abort();
+        try {
+            await fetch("https://httpbin.org/get", { signal: controller.signal });
+            console.log("❌ ERROR: Aborted fetch should have rejected!");
+        } catch (error) {
+            console.log(`✅ Aborted fetch rejected with: ${error.name}`);
+        }
+
+        const timeoutResponse = await fetch("https://httpbin.org/get", {
+            signal: AbortSignal.timeout(5000)
+        });
+        console.log(`Request with 5s timeout signal: ${timeoutResponse.status}`);
+
+        // Example 18: Blob and File
+        console.log("\n18. Blob and File:");
+        const pngBlob = await (await fetch("https://httpbin.org/image/png")).blob();
+        console.log(`Blob size: ${pngBlob.size} bytes, type: ${pngBlob.type}`);
+        console.log(`PNG signature: ${Array.from(await pngBlob.slice(1, 4).bytes()).map(b => String.fromCharCode(b)).join('')}`);
+
+        const file = new File(["Hello, ", "Blockless!"], "hello.txt", { type: "text/plain" });
+        const fileResponse = await fetch("https://httpbin.org/post", {
+            method: "POST",
+            body: file
+        });
+        const fileResult = await fileResponse.json();
+        console.log(`Uploaded ${file.name}: ${fileResult.data} (${fileResult.headers['Content-Type']})`);
+
+        // Example 19: Multipart FormData with files
+        console.log("\n19. Multipart FormData:");
+        const form = new FormData();
+        form.append("tag", "first");
+        form.append("tag", "second");
+        form.append("upload", file);
+        form.append("png", pngBlob, "image.png");
+        const formResponse = await fetch("https://httpbin.org/post", {
+            method: "POST",
+            body: form
+        });
+        const formResult = await formResponse.json();
+        console.log(`Form fields: ${JSON.stringify(formResult.form)}`);
+        console.log(`Uploaded files: ${Object.keys(formResult.files).join(', ')}`);
+
+        // Example 20: Decoding form-encoded responses
+        console.log("\n20. Response formData():");
+        const webhookReply = new Response("status=ok&id=42&tag=a&tag=b", {
+            headers: { "Content-Type": "application/x-www-form-urlencoded" }
+        });
+        const reply = await webhookReply.formData();
+        console.log(`status=${reply.get('status')}, id=${reply.get('id')}, tags=${reply.getAll('tag').join(',')}`);
+
+        // Example 21: URL and URLSearchParams
+        console.log("\n21. URL objects:");
+        const apiUrl = new URL("/get", "https://httpbin.org");
+        apiUrl.searchParams.set("query", "bless network");
+        apiUrl.searchParams.append("page", "1");
+        console.log(`Request URL: ${apiUrl.href}`);
+        const urlResponse = await fetch(apiUrl);
+        const urlResult = await urlResponse.json();
+        console.log(`Query args: ${JSON.stringify(urlResult.args)}`);
+        try {
+            await fetch("not a valid url");
+        } catch (error) {
+            console.log(`✅ Malformed URL rejected with: ${error.name}`);
+        }
+
+        // Example 22: Redirect handling
+        console.log("\n22. Redirects:");
+        const followed = await fetch("https://httpbin.org/redirect/2");
+        console.log(`Followed: redirected=${followed.redirected}, final URL: ${followed.url}`);
+        const manual = await fetch("https://httpbin.org/redirect/1", { redirect: "manual" });
+        console.log(`Manual: type=${manual.type}, status=${manual.status}`);
+        try {
+            await fetch("https://httpbin.org/redirect/1", { redirect: "error" });
+        } catch (error) {
+            console.log(`✅ Redirect rejected in error mode: ${error.message}`);
+        }
+
+        // Example 23: Repeated headers and Set-Cookie
+        console.log("\n23. Set-Cookie headers:");
+        const cookieResponse = await fetch("https://httpbin.org/response-headers?Set-Cookie=session=abc&Set-Cookie=theme=dark");
+        console.log(`Cookies: ${JSON.stringify(cookieResponse.headers.getSetCookie())}`);
+        for (const [name, value] of cookieResponse.headers) {
+            console.log(`  ${name}: ${value}`);
+        }
+
+        // Example 24: Streaming response bodies, fetched with range requests when chunkSize is set
+        console.log("\n24. Streaming response bodies:");
+        const streamed = await fetch("https://httpbin.org/range/100000", { chunkSize: 32 * 1024 });
+        let received = 0;
+        for await (const chunk of streamed.body) {
+            received += chunk.byteLength;
+            console.log(`  chunk of ${chunk.byteLength} bytes (${received} total)`);
+        }
+        const partial = await fetch("https://httpbin.org/range/1024", { chunkSize: 256 });
+        const reader = partial.body.getReader();
+        const { value: first } = await reader.read();
+        console.log(`First chunk: ${first.byteLength} bytes, cancelling the rest`);
+        await reader.cancel();
+
+        // Example 25: Streamed request bodies, collected outside of QuickJS memory (up to 32 MiB)
+        // and sent with a single host request
+        console.log("\n25. Streaming uploads:");
+        async function* ndjson() {
+            for (let i = 1; i <= 3; i++) {
+                yield JSON.stringify({ event: i }) + "\n";
+            }
+        }
+        const uploadResponse = await fetch("https://httpbin.org/post", {
+            method: "POST",
+            headers: { "Content-Type": "application/x-ndjson" },
+            body: ndjson(),
+            duplex: "half"
+        });
+        const uploadResult = await uploadResponse.json();
+        console.log(`Uploaded ${uploadResult.data.length} bytes from an async generator`);
+        if (globalThis.wasi_fs) {
+            // Files opened with wasi_fs are read from the file descriptor outside of QuickJS memory,
+            // whole and up to 32 MiB like streamed bodies
+            const file = wasi_fs.open("/data/upload.bin", "r");
+            if (file) {
+                const fileResponse = await fetch("https://httpbin.org/put", { method: "PUT", body: file });
+                console.log(`File upload status: ${fileResponse.status}`);
+                file.close();
+            }
+        }
+
+        // Example 26: Server-Sent Events, delivered once each response has completed
+        console.log("\n26. Server-Sent Events:");
+        for await (const event of fetchEventStream("https://sse.dev/test?interval=1&limit=2")) {
+            console.log(`  ${event.event}: ${event.data}`);
+        }
+        await new Promise((resolve) => {
+            const source = new EventSource("https://sse.dev/test?limit=1");
+            source.onmessage = (event) => {
+                console.log(`EventSource message: ${event.data}`);
+                source.close();
+                resolve();
+            };
+            source.onerror = () => {
+                if (source.readyState === EventSource.CLOSED) {
+                    resolve();
+                }
+            };
+        });
+
+        // Example 27: Starting requests together. They are sent one after another, since each
+        // host call blocks until its response arrives, so this takes as long as three fetches in a row.
+        console.log("\n27. Fetches started together, sent one at a time:");
+        const started = Date.now();
+        const fanOut = await Promise.all(
+            [1, 2, 3].map((id) => fetch(`https://jsonplaceholder.typicode.com/posts/${id}`).then((r) => r.json()))
+        );
+        console.log(`Fetched ${fanOut.map((post) => post.id).join(', ')} in ${Date.now() - started} ms`);
+
+        // Example 28: Telling failures apart
+        console.log("\n28. Typed fetch errors:");
+        for (const target of ["https://does-not-exist.invalid/", "https://httpbin.org/delay/5"]) {
+            try {
+                await fetch(target, { signal: AbortSignal.timeout(2000) });
+            } catch (error) {
+                if (error.name === "TimeoutError") {
+                    console.log(`✅ ${target} timed out`);
+                } else if (error instanceof TypeError) {
+                    console.log(`✅ ${target} failed with ${error.code}: ${error.cause ? error.cause.message : error.message}`);
+                } else {
+                    throw error;
+                }
+            }
+        }
+
+        // Example 29: Retrying transient failures
+        console.log("\n29. Retry with backoff:");
+        const retried = await fetch("https://httpbin.org/status/503", {
+            retry: { retries: 2, backoff: "exponential", delay: 200, retryOn: [503], respectRetryAfter: true }
+        });
+        console.log(`✅ Gave up after retries with status ${retried.status}`);
+
+        // Example 30: Subresource Integrity
+        console.log("\n30. Integrity checks:");
+        const checked = await fetch("https://httpbin.org/robots.txt", {
+            integrity: "sha256-gJ+Nd+IaNcvo4E6jMZOxEi1JvflTwd+mRFBuh0a7Jbg="
+        }).catch((error) => error);
+        console.log(checked instanceof Response
+            ? "✅ Body matches its integrity metadata"
+            : `✅ Rejected with ${checked.code}: ${checked.message}`);
+
+        // Example 31: HTTP cache
+        console.log("\n31. HTTP cache:");
+        await fetch("https://httpbin.org/cache/60").then((r) => r.text());
+        const cachedResponse = await fetch("https://httpbin.org/cache/60", { cache: "only-if-cached" });
+        console.log(`✅ Served from cache, age ${cachedResponse.headers.get("age")}s`);
+        const revalidated = await fetch("https://httpbin.org/etag/reference-data", { cache: "no-cache" });
+        console.log(`✅ Revalidated with the server: ${revalidated.status}`);
+        fetchCache.clear();
+
+        // Example 32: Cookie jar
+        console.log("\n32. Cookie jar:");
+        fetchCookies.enable();
+        await fetch("https://httpbin.org/cookies/set?session=demo", { credentials: "include" });
+        const withCookies = await fetch("https://httpbin.org/cookies", { credentials: "include" }).then((r) => r.json());
+        console.log(`✅ Server saw cookies: ${JSON.stringify(withCookies.cookies)}`);
+        console.log(`✅ Jar holds: ${fetchCookies.getAll().map((cookie) => `${cookie.name}@${cookie.domain}`).join(", ")}`);
+        fetchCookies.clear();
+        fetchCookies.disable();
+
+        // Example 33: Compressed responses
+        console.log("\n33. Response decompression:");
+        for (const coding of ["gzip", "deflate", "brotli"]) {
+            const compressed = await fetch(`https://httpbin.org/${coding}`);
+            const decoded = await compressed.json();
+            console.log(`✅ ${coding}: ${compressed.headers.get("content-length")} decoded bytes, ${Object.keys(decoded).length} fields`);
+        }
+        const raw = await fetch("https://httpbin.org/gzip", { decompress: false });
+        console.log(`✅ Raw ${raw.headers.get("content-encoding")} body: ${(await raw.arrayBuffer()).byteLength} bytes`);
+
+        // Example 34: Compressing uploads
+        console.log("\n34. Compressed uploads:");
+        const payload = JSON.stringify({ items: Array.from({ length: 100 }, (_, i) => ({ id: i, name: `item ${i}` })) });
+        const gzipped = BlessCompression.compress(payload, "gzip");
+        console.log(`✅ BlessCompression.compress(): ${payload.length} bytes -> ${gzipped.length} bytes, round trip ok: ${new TextDecoder().decode(BlessCompression.decompress(gzipped, "gzip")) === payload}`);
+        const upload = await fetch("https://httpbin.org/post", {
+            method: "POST",
+            headers: { "Content-Type": "application/json", "Content-Encoding": "gzip" },
+            body: gzipped,
+        });
+        console.log(`✅ Uploaded gzip payload: status ${upload.status}`);
+        const streamed = new Response(payload).body.pipeThrough(new CompressionStream("deflate-raw"));
+        const restored = await new Response(streamed.pipeThrough(new DecompressionStream("deflate-raw"))).text();
+        console.log(`✅ CompressionStream -> DecompressionStream round trip ok: ${restored === payload}`);
+
         console.log("\n✅ All fetch examples completed successfully!");
         
     } catch (error) {
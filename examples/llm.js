@@ -6,13 +6,59 @@ const llm = BlessLLM(MODELS.MISTRAL_7B.DEFAULT);
 
 // Set options
 llm.setOptions({
-  system_message: "You are a helpful assistant. First time I ask, your name will be Lucy. Second time I ask, your name will be Bob."
+  system_message: "You are a helpful assistant. First time I ask, your name will be Lucy. Second time I ask, your name will be Bob.",
+  // Sampling parameters are optional, values out of range throw a RangeError, and setOptions
+  // throws when the host does not apply one of them
+  temperature: 0.7,
+  max_tokens: 512,
+  stop: ["\n\nUser:"],
+  seed: 42,
 });
 
-// Get options
+// Get options, as the host reports them
 const options = llm.getOptions();
 console.log("Options", JSON.stringify(options, null, 2));
 
 // Chat
 console.log(llm.chat("What is your name?"));
 console.log(llm.chat("What is your name?"));
+
+// Inspect the conversation so far, then start over
+console.log("History", JSON.stringify(llm.history(), null, 2));
+llm.reset();
+
+// Replay a structured conversation; the reply is an assistant message. The host takes a single
+// prompt, so the messages are sent as one transcript, which must fit in 65535 bytes
+const reply = llm.chatMessages([
+  { role: "system", content: "You are a helpful assistant that answers in one word." },
+  { role: "user", content: "What is the capital of Spain?" },
+  { role: "assistant", content: "Madrid." },
+  { role: "user", content: "And of France?" },
+]);
+console.log(reply.role, reply.content);
+
+// Fork the conversation from an earlier point
+const forked = llm.chatMessages([...llm.history().slice(0, 2), { role: "user", content: "And of Italy?" }]);
+console.log(forked.content);
+
+// Ask for structured output: the reply is validated against the JSON Schema, and the
+// model is asked to repair it (twice by default) when it does not conform. Only the valid reply
+// is kept in the history, and schemas using keywords such as $ref or pattern throw a TypeError
+const city = llm.chatJSON("Describe the capital of Spain.", {
+  type: "object",
+  properties: {
+    name: { type: "string" },
+    population: { type: "integer", minimum: 0 },
+    landmarks: { type: "array", items: { type: "string" }, maxItems: 3 },
+  },
+  required: ["name", "population"],
+}, { retries: 3 });
+console.log(city.name, city.population, city.landmarks);
+
+// chatAsync is chat behind a promise. The request runs after the current turn and blocks the
+// module until the model replies, so requests run one at a time and do not overlap with fetch
+(async () => {
+  const answer = await llm.chatAsync("Summarize the plot of Hamlet in one sentence.");
+  const followUp = await llm.chatAsync("Who is the main character?");
+  console.log(answer, followUp);
+})();
@@ -0,0 +1,107 @@
+use flate2::{Decompress, FlushDecompress, Status};
+use std::io::{self, Write};
+
+/// Most bytes a single decompression may produce, so that a small compressed input cannot
+/// expand into more memory than the module has
+pub const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// The buffer a decoder writes its output into. Writes fail once `MAX_DECOMPRESSED_SIZE` bytes
+/// were written in total, including the output already taken out of it.
+#[derive(Default)]
+pub struct BoundedOutput {
+    buffer: Vec<u8>,
+    written: usize,
+}
+
+impl BoundedOutput {
+    /// Take the output written since the last call
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+impl Write for BoundedOutput {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        if bytes.len() > MAX_DECOMPRESSED_SIZE - self.written {
+            return Err(io::Error::other(format!(
+                "decompressed data is larger than the {} MiB limit",
+                MAX_DECOMPRESSED_SIZE / (1024 * 1024)
+            )));
+        }
+        self.written += bytes.len();
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Size of the buffer each inflate step decompresses into
+const INFLATE_BUFFER_SIZE: usize = 32 * 1024;
+
+/// A zlib or raw deflate decoder fed chunk by chunk, writing into a `BoundedOutput`.
+///
+/// Unlike the `flate2::write` decoders, it knows where the compressed stream ends: data cut
+/// short fails to finish, and data written past the end is rejected.
+pub struct Inflater {
+    decompress: Decompress,
+    output: BoundedOutput,
+    ended: bool,
+}
+
+impl Inflater {
+    /// A decoder for zlib wrapped data (RFC 1950), or raw deflate data (RFC 1951)
+    pub fn new(zlib_header: bool) -> Self {
+        Self {
+            decompress: Decompress::new(zlib_header),
+            output: BoundedOutput::default(),
+            ended: false,
+        }
+    }
+
+    /// Decompress a chunk, returning the output produced so far
+    pub fn write(&mut self, mut input: &[u8]) -> io::Result<Vec<u8>> {
+        loop {
+            if self.ended {
+                if !input.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unexpected data after the end of the compressed stream",
+                    ));
+                }
+                break;
+            }
+            let mut buffer = Vec::with_capacity(INFLATE_BUFFER_SIZE);
+            let total_in = self.decompress.total_in();
+            let status =
+                self.decompress
+                    .decompress_vec(input, &mut buffer, FlushDecompress::None)?;
+            input = &input[(self.decompress.total_in() - total_in) as usize..];
+            self.output.write_all(&buffer)?;
+            self.ended = status == Status::StreamEnd;
+            // Stop once the input is used up and the decoder has no more output to give
+            if input.is_empty() && buffer.len() < INFLATE_BUFFER_SIZE {
+                break;
+            }
+        }
+        Ok(self.output.take())
+    }
+
+    /// Return the remaining output, failing if the compressed stream did not end
+    pub fn finish(mut self) -> io::Result<Vec<u8>> {
+        let output = self.write(&[])?;
+        if !self.ended {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the compressed stream is truncated",
+            ));
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,243 @@
+// Wrap everything in an anonymous function to avoid leaking local variables into the global scope.
+(function () {
+    // Get a reference to the functions before we delete them from `globalThis`.
+    const __javy_bless_compression_create = globalThis.__javy_bless_compression_create;
+    const __javy_bless_compression_write = globalThis.__javy_bless_compression_write;
+    const __javy_bless_compression_finish = globalThis.__javy_bless_compression_finish;
+    const __javy_bless_compress = globalThis.__javy_bless_compress;
+    const __javy_bless_decompress = globalThis.__javy_bless_decompress;
+
+    const encoder = new TextEncoder();
+
+    // Data to compress may be a string (encoded as UTF-8), an ArrayBuffer or any ArrayBuffer view
+    function toBytes(data, name) {
+        if (typeof data === 'string') {
+            return encoder.encode(data);
+        }
+        if (data instanceof Uint8Array) {
+            return data;
+        }
+        if (data instanceof ArrayBuffer) {
+            return new Uint8Array(data);
+        }
+        if (ArrayBuffer.isView(data)) {
+            return new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+        }
+        throw new TypeError(`${name}: expected a string, an ArrayBuffer or an ArrayBufferView`);
+    }
+
+    // Provided by streams.js, evaluated before this file
+    const ReadableStream = globalThis.ReadableStream;
+
+    // Writable side of the compression streams. The sink processes chunks synchronously,
+    // so writes complete right away and there is no backpressure to report.
+    class WritableStream {
+        constructor(sink) {
+            this._sink = sink;
+            this._state = 'writable';
+            this._error = undefined;
+            this._locked = false;
+        }
+
+        get locked() { return this._locked; }
+
+        _write(chunk) {
+            if (this._state === 'errored') {
+                return Promise.reject(this._error);
+            }
+            if (this._state !== 'writable') {
+                return Promise.reject(new TypeError('WritableStream is closed'));
+            }
+            try {
+                this._sink.write(chunk);
+                return Promise.resolve();
+            } catch (e) {
+                this._state = 'errored';
+                this._error = e;
+                return Promise.reject(e);
+            }
+        }
+
+        _close() {
+            if (this._state === 'errored') {
+                return Promise.reject(this._error);
+            }
+            if (this._state !== 'writable') {
+                return Promise.reject(new TypeError('WritableStream is closed'));
+            }
+            try {
+                this._sink.close();
+                this._state = 'closed';
+                return Promise.resolve();
+            } catch (e) {
+                this._state = 'errored';
+                this._error = e;
+                return Promise.reject(e);
+            }
+        }
+
+        _abort(reason) {
+            if (this._state === 'writable') {
+                this._state = 'errored';
+                this._error = reason;
+                this._sink.abort(reason);
+            }
+            return Promise.resolve();
+        }
+
+        abort(reason) {
+            if (this._locked) {
+                return Promise.reject(new TypeError('WritableStream is locked'));
+            }
+            return this._abort(reason);
+        }
+
+        getWriter() {
+            if (this._locked) {
+                throw new TypeError('WritableStream is locked');
+            }
+            this._locked = true;
+            const stream = this;
+            let released = false;
+            const check = (operation) => released
+                ? Promise.reject(new TypeError('Writer has been released'))
+                : operation();
+            return {
+                write: (chunk) => check(() => this._write(chunk)),
+                close: () => check(() => this._close()),
+                abort: (reason) => check(() => this._abort(reason)),
+                releaseLock: () => {
+                    released = true;
+                    this._locked = false;
+                },
+                get ready() { return Promise.resolve(); },
+                get desiredSize() { return stream._state === 'writable' ? 1 : 0; },
+                get closed() {
+                    if (stream._state === 'errored') {
+                        return Promise.reject(stream._error);
+                    }
+                    return Promise.resolve();
+                },
+            };
+        }
+    }
+
+    // Connect a native compression or decompression handle to a writable and a readable side.
+    // Output produced by each write is queued until the readable side is read.
+    function createTransform(stream, format, decompress, name) {
+        const handle = __javy_bless_compression_create(String(format), decompress);
+        const output = [];
+        let finished = false;
+        let error = undefined;
+        let errored = false;
+        let wake = null;
+
+        const notify = () => {
+            if (wake) {
+                const resolve = wake;
+                wake = null;
+                resolve();
+            }
+        };
+        const push = (bytes) => {
+            if (bytes.length > 0) {
+                output.push(bytes);
+            }
+        };
+        const fail = (e) => {
+            if (!finished && !errored) {
+                errored = true;
+                error = e;
+                notify();
+            }
+        };
+
+        stream.readable = new ReadableStream({
+            async pull(controller) {
+                while (output.length === 0 && !finished && !errored) {
+                    await new Promise(resolve => { wake = resolve; });
+                }
+                if (errored) {
+                    controller.error(error);
+                } else if (output.length > 0) {
+                    controller.enqueue(output.shift());
+                } else {
+                    controller.close();
+                }
+            },
+            cancel(reason) {
+                output.length = 0;
+                fail(reason);
+            },
+        });
+
+        stream.writable = new WritableStream({
+            write(chunk) {
+                try {
+                    push(__javy_bless_compression_write(handle, toBytes(chunk, name)));
+                } catch (e) {
+                    fail(e);
+                    throw e;
+                }
+                notify();
+            },
+            close() {
+                try {
+                    push(__javy_bless_compression_finish(handle));
+                } catch (e) {
+                    fail(e);
+                    throw e;
+                }
+                finished = true;
+                notify();
+            },
+            abort(reason) {
+                fail(reason);
+            },
+        });
+    }
+
+    // Compresses the chunks written to `writable` with 'gzip', 'deflate' or 'deflate-raw',
+    // the compressed data is read from `readable`
+    class CompressionStream {
+        constructor(format) {
+            createTransform(this, format, false, 'CompressionStream');
+        }
+
+        get [Symbol.toStringTag]() { return 'CompressionStream'; }
+    }
+
+    // Decompresses the chunks written to `writable`, corrupt or truncated data errors `readable`
+    class DecompressionStream {
+        constructor(format) {
+            createTransform(this, format, true, 'DecompressionStream');
+        }
+
+        get [Symbol.toStringTag]() { return 'DecompressionStream'; }
+    }
+
+    // Compress a whole buffer at once, returning a Uint8Array
+    function compress(data, format = 'gzip') {
+        return __javy_bless_compress(toBytes(data, 'compress'), String(format));
+    }
+
+    // Decompress a whole buffer at once, returning a Uint8Array
+    function decompress(data, format = 'gzip') {
+        return __javy_bless_decompress(toBytes(data, 'decompress'), String(format));
+    }
+
+    // One-shot helpers, exposed as `BlessCompression.compress` and `BlessCompression.decompress`
+    // rather than as globals, to keep the generic names out of the global scope
+    const BlessCompression = Object.freeze({ compress, decompress });
+
+    globalThis.CompressionStream = CompressionStream;
+    globalThis.DecompressionStream = DecompressionStream;
+    globalThis.BlessCompression = BlessCompression;
+
+    // Delete the functions from `globalThis` so they don't leak.
+    Reflect.deleteProperty(globalThis, "__javy_bless_compression_create");
+    Reflect.deleteProperty(globalThis, "__javy_bless_compression_write");
+    Reflect.deleteProperty(globalThis, "__javy_bless_compression_finish");
+    Reflect.deleteProperty(globalThis, "__javy_bless_compress");
+    Reflect.deleteProperty(globalThis, "__javy_bless_decompress");
+})();
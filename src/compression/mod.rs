@@ -0,0 +1,344 @@
+use anyhow::{anyhow, bail, Result};
+use flate2::{
+    write::{DeflateEncoder, GzDecoder, GzEncoder, ZlibEncoder},
+    Compression,
+};
+use javy_plugin_api::javy::{
+    quickjs::{
+        class::{ClassId, JsClass, Trace, Tracer, Writable},
+        function::Constructor,
+        Class, Ctx, Exception, Object, TypedArray, Value,
+    },
+    Args,
+};
+use std::{
+    io::{self, Write},
+    mem::take,
+};
+
+use crate::bounded::{BoundedOutput, Inflater};
+
+/// Formats supported by `CompressionStream` and `DecompressionStream`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Gzip (RFC 1952)
+    Gzip,
+    /// Zlib wrapped deflate (RFC 1950)
+    Deflate,
+    /// Raw deflate without a wrapper (RFC 1951)
+    DeflateRaw,
+}
+
+impl Format {
+    fn parse(format: &str) -> Option<Self> {
+        match format {
+            "gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            "deflate-raw" => Some(Self::DeflateRaw),
+            _ => None,
+        }
+    }
+}
+
+/// A compression or decompression in progress, fed chunk by chunk.
+/// Decompression fails once it produced more than `MAX_DECOMPRESSED_SIZE` bytes.
+pub enum Codec {
+    GzipEncoder(GzEncoder<Vec<u8>>),
+    ZlibEncoder(ZlibEncoder<Vec<u8>>),
+    DeflateEncoder(DeflateEncoder<Vec<u8>>),
+    GzipDecoder(GzDecoder<BoundedOutput>),
+    /// Zlib wrapped or raw deflate data
+    Inflater(Inflater),
+    /// The stream was finished, no more chunks are accepted
+    Finished,
+}
+
+impl Codec {
+    fn new(format: Format, decompress: bool) -> Self {
+        let level = Compression::default();
+        match (format, decompress) {
+            (Format::Gzip, false) => Self::GzipEncoder(GzEncoder::new(Vec::new(), level)),
+            (Format::Deflate, false) => Self::ZlibEncoder(ZlibEncoder::new(Vec::new(), level)),
+            (Format::DeflateRaw, false) => {
+                Self::DeflateEncoder(DeflateEncoder::new(Vec::new(), level))
+            }
+            (Format::Gzip, true) => Self::GzipDecoder(GzDecoder::new(BoundedOutput::default())),
+            (Format::Deflate, true) => Self::Inflater(Inflater::new(true)),
+            (Format::DeflateRaw, true) => Self::Inflater(Inflater::new(false)),
+        }
+    }
+
+    fn is_decoder(&self) -> bool {
+        matches!(self, Self::GzipDecoder(_) | Self::Inflater(_))
+    }
+
+    /// Process a chunk, returning the output produced so far
+    fn write(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::GzipEncoder(codec) => codec.write_all(chunk).map(|_| take(codec.get_mut())),
+            Self::ZlibEncoder(codec) => codec.write_all(chunk).map(|_| take(codec.get_mut())),
+            Self::DeflateEncoder(codec) => codec.write_all(chunk).map(|_| take(codec.get_mut())),
+            Self::GzipDecoder(codec) => codec.write_all(chunk).map(|_| codec.get_mut().take()),
+            Self::Inflater(codec) => codec.write(chunk),
+            Self::Finished => Err(io::Error::other("the stream is already closed")),
+        }
+    }
+
+    /// Flush the remaining output and end the stream
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        match std::mem::replace(self, Self::Finished) {
+            Self::GzipEncoder(codec) => codec.finish(),
+            Self::ZlibEncoder(codec) => codec.finish(),
+            Self::DeflateEncoder(codec) => codec.finish(),
+            Self::GzipDecoder(codec) => codec.finish().map(BoundedOutput::into_inner),
+            Self::Inflater(codec) => codec.finish(),
+            Self::Finished => Ok(Vec::new()),
+        }
+    }
+
+    /// Compress or decompress a whole buffer
+    fn run(mut self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = self.write(bytes)?;
+        output.extend(self.finish()?);
+        Ok(output)
+    }
+}
+
+impl<'js> Trace<'js> for Codec {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+impl<'js> JsClass<'js> for Codec {
+    const NAME: &'static str = "NativeCompressionStream";
+
+    type Mutable = Writable;
+
+    fn class_id() -> &'static ClassId {
+        static ID: ClassId = ClassId::new();
+        &ID
+    }
+
+    fn prototype(ctx: &Ctx<'js>) -> javy_plugin_api::javy::quickjs::Result<Option<Object<'js>>> {
+        Object::new(ctx.clone()).map(Some)
+    }
+
+    fn constructor(
+        _ctx: &Ctx<'js>,
+    ) -> javy_plugin_api::javy::quickjs::Result<Option<Constructor<'js>>> {
+        Ok(None)
+    }
+}
+
+fn format_arg(cx: &Ctx<'_>, value: Option<&Value<'_>>) -> Result<Format> {
+    let format = value
+        .and_then(Value::as_string)
+        .map(|format| format.to_string())
+        .transpose()?
+        .unwrap_or_default();
+    Format::parse(&format).ok_or_else(|| {
+        Exception::throw_type(
+            cx,
+            &format!(
+                "Unsupported compression format: '{}', expected 'gzip', 'deflate' or 'deflate-raw'",
+                format
+            ),
+        )
+        .into()
+    })
+}
+
+fn bytes_arg<'js>(value: Option<&Value<'js>>, for_func: &str) -> Result<TypedArray<'js, u8>> {
+    value
+        .and_then(|value| TypedArray::<u8>::from_value(value.clone()).ok())
+        .ok_or_else(|| anyhow!("{} expects a Uint8Array", for_func))
+}
+
+fn handle_arg<'js>(value: Option<&Value<'js>>, for_func: &str) -> Result<Class<'js, Codec>> {
+    value
+        .and_then(Value::as_object)
+        .and_then(Class::<Codec>::from_object)
+        .ok_or_else(|| anyhow!("{}: invalid compression stream handle", for_func))
+}
+
+/// Corrupt or truncated input is reported as a `TypeError`, as `DecompressionStream` does
+fn codec_error(cx: &Ctx<'_>, decompress: bool, error: io::Error) -> anyhow::Error {
+    let action = if decompress { "decompress" } else { "compress" };
+    Exception::throw_type(cx, &format!("Failed to {} data: {}", action, error)).into()
+}
+
+fn to_bytes<'js>(cx: Ctx<'js>, bytes: &[u8]) -> Result<Value<'js>> {
+    Ok(TypedArray::<u8>::new_copy(cx, bytes)?.into_value())
+}
+
+/// Create a compression (or, when the second argument is `true`, decompression) stream handle
+/// for the given format.
+pub fn bless_compression_create(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let format = format_arg(&cx, args.first())?;
+    let decompress = args.get(1).and_then(Value::as_bool).unwrap_or(false);
+    Ok(Class::instance(cx, Codec::new(format, decompress))?.into_value())
+}
+
+/// Feed a chunk to a stream handle, returning the output it produced (possibly empty).
+pub fn bless_compression_write(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let [handle, chunk, ..] = args.as_slice() else {
+        bail!(
+            "compression_write expects 2 parameters: the stream handle and a chunk, Got: {} parameters.",
+            args.len()
+        );
+    };
+    let codec = handle_arg(Some(handle), "compression_write")?;
+    let chunk = bytes_arg(Some(chunk), "compression_write")?;
+    let chunk = chunk
+        .as_bytes()
+        .ok_or_else(|| anyhow!("ArrayBuffer is detached"))?;
+
+    let mut codec = codec.borrow_mut();
+    let output = codec
+        .write(chunk)
+        .map_err(|e| codec_error(&cx, codec.is_decoder(), e))?;
+    to_bytes(cx, &output)
+}
+
+/// End a stream handle, returning its remaining output.
+pub fn bless_compression_finish(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let codec = handle_arg(args.first(), "compression_finish")?;
+    let mut codec = codec.borrow_mut();
+    let decompress = codec.is_decoder();
+    let output = codec
+        .finish()
+        .map_err(|e| codec_error(&cx, decompress, e))?;
+    to_bytes(cx, &output)
+}
+
+/// Compress a whole buffer in one call.
+pub fn bless_compress(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let bytes = bytes_arg(args.first(), "compress")?;
+    let format = format_arg(&cx, args.get(1))?;
+    let output = Codec::new(format, false)
+        .run(
+            bytes
+                .as_bytes()
+                .ok_or_else(|| anyhow!("ArrayBuffer is detached"))?,
+        )
+        .map_err(|e| codec_error(&cx, false, e))?;
+    to_bytes(cx, &output)
+}
+
+/// Decompress a whole buffer in one call.
+pub fn bless_decompress(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let bytes = bytes_arg(args.first(), "decompress")?;
+    let format = format_arg(&cx, args.get(1))?;
+    let output = Codec::new(format, true)
+        .run(
+            bytes
+                .as_bytes()
+                .ok_or_else(|| anyhow!("ArrayBuffer is detached"))?,
+        )
+        .map_err(|e| codec_error(&cx, true, e))?;
+    to_bytes(cx, &output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounded::MAX_DECOMPRESSED_SIZE;
+
+    const TEXT: &[u8] = b"hello compressed world! hello compressed world! hello compressed world!";
+
+    const FORMATS: [Format; 3] = [Format::Gzip, Format::Deflate, Format::DeflateRaw];
+
+    /// Feed a buffer to a new codec in chunks of `chunk_size` bytes
+    fn stream(format: Format, decompress: bool, bytes: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut codec = Codec::new(format, decompress);
+        let mut output = Vec::new();
+        for chunk in bytes.chunks(chunk_size) {
+            output.extend(codec.write(chunk).unwrap());
+        }
+        output.extend(codec.finish().unwrap());
+        output
+    }
+
+    #[test]
+    fn formats_are_parsed() {
+        assert_eq!(Format::parse("gzip"), Some(Format::Gzip));
+        assert_eq!(Format::parse("deflate"), Some(Format::Deflate));
+        assert_eq!(Format::parse("deflate-raw"), Some(Format::DeflateRaw));
+        assert_eq!(Format::parse("GZIP"), None);
+        assert_eq!(Format::parse("br"), None);
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        for format in FORMATS {
+            let compressed = Codec::new(format, false).run(TEXT).unwrap();
+            assert_ne!(compressed, TEXT);
+            assert_eq!(Codec::new(format, true).run(&compressed).unwrap(), TEXT);
+        }
+    }
+
+    #[test]
+    fn every_format_round_trips_in_chunks() {
+        for format in FORMATS {
+            let compressed = stream(format, false, TEXT, 5);
+            for chunk_size in [1, 7, compressed.len()] {
+                assert_eq!(stream(format, true, &compressed, chunk_size), TEXT);
+            }
+        }
+    }
+
+    #[test]
+    fn formats_are_not_interchangeable() {
+        let gzip = Codec::new(Format::Gzip, false).run(TEXT).unwrap();
+        assert!(Codec::new(Format::Deflate, true).run(&gzip).is_err());
+        let zlib = Codec::new(Format::Deflate, false).run(TEXT).unwrap();
+        assert!(Codec::new(Format::Gzip, true).run(&zlib).is_err());
+    }
+
+    #[test]
+    fn truncated_and_corrupt_input_fails() {
+        for format in FORMATS {
+            let compressed = Codec::new(format, false).run(TEXT).unwrap();
+            let truncated = &compressed[..compressed.len() / 2];
+            assert!(Codec::new(format, true).run(truncated).is_err());
+        }
+        assert!(Codec::new(Format::Gzip, true)
+            .run(b"not gzip at all")
+            .is_err());
+        let mut zlib = Codec::new(Format::Deflate, false).run(TEXT).unwrap();
+        zlib.push(0);
+        assert!(Codec::new(Format::Deflate, true).run(&zlib).is_err());
+    }
+
+    #[test]
+    fn finished_streams_reject_chunks() {
+        let mut codec = Codec::new(Format::Gzip, false);
+        codec.finish().unwrap();
+        assert!(codec.write(TEXT).is_err());
+        assert!(codec.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn decompression_bombs_are_rejected() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder
+            .write_all(&vec![0; MAX_DECOMPRESSED_SIZE + 1])
+            .unwrap();
+        let bomb = encoder.finish().unwrap();
+        assert!(bomb.len() < 1024 * 1024);
+
+        let error = Codec::new(Format::Gzip, true).run(&bomb).unwrap_err();
+        assert!(error.to_string().contains("64 MiB limit"));
+
+        let mut codec = Codec::new(Format::Gzip, true);
+        let result = bomb
+            .chunks(64 * 1024)
+            .try_for_each(|chunk| codec.write(chunk).map(drop))
+            .and_then(|_| codec.finish());
+        assert!(result.is_err());
+    }
+}
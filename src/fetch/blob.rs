@@ -0,0 +1,243 @@
+use anyhow::{anyhow, bail, Result};
+use javy_plugin_api::javy::{
+    quickjs::{
+        class::{ClassId, JsClass, Readable, Trace, Tracer},
+        function::Constructor,
+        Class, Ctx, Object, String as JSString, TypedArray, Value,
+    },
+    Args,
+};
+use std::rc::Rc;
+
+use super::read_binary_data;
+
+/// Bytes backing a JavaScript `Blob`.
+/// The bytes live in Rust memory and are shared between a blob and its slices.
+#[derive(Clone)]
+pub struct BlobData {
+    bytes: Rc<[u8]>,
+    start: usize,
+    end: usize,
+}
+
+impl BlobData {
+    pub fn new(bytes: Rc<[u8]>) -> Self {
+        let end = bytes.len();
+        Self {
+            bytes,
+            start: 0,
+            end,
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[self.start..self.end]
+    }
+
+    /// A view onto `start..end` of this blob, sharing the same bytes
+    pub fn slice(&self, start: usize, end: usize) -> Self {
+        let end = end.clamp(0, self.len());
+        let start = start.min(end);
+        Self {
+            bytes: self.bytes.clone(),
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+
+    /// A view as `Blob.slice` takes it: negative indexes count from the end, and both are
+    /// clamped to the blob. A missing start is 0 and a missing end is the length.
+    pub fn slice_relative(&self, start: Option<f64>, end: Option<f64>) -> Self {
+        let start = start.map_or(0, |start| relative_index(start, self.len()));
+        let end = end.map_or(self.len(), |end| relative_index(end, self.len()));
+        self.slice(start, end.max(start))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Wrap the data in a native handle object that can be passed back to Rust
+    pub fn into_js(self, cx: Ctx<'_>) -> Result<Value<'_>> {
+        Ok(Class::instance(cx, self)?.into_value())
+    }
+
+    /// Get the blob data behind a native handle, if the object is one
+    pub fn from_object(obj: &Object<'_>) -> Option<Self> {
+        Class::<BlobData>::from_object(obj).map(|class| class.borrow().clone())
+    }
+}
+
+impl<'js> Trace<'js> for BlobData {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+impl<'js> JsClass<'js> for BlobData {
+    const NAME: &'static str = "NativeBlob";
+
+    type Mutable = Readable;
+
+    fn class_id() -> &'static ClassId {
+        static ID: ClassId = ClassId::new();
+        &ID
+    }
+
+    fn prototype(ctx: &Ctx<'js>) -> javy_plugin_api::javy::quickjs::Result<Option<Object<'js>>> {
+        Object::new(ctx.clone()).map(Some)
+    }
+
+    fn constructor(
+        _ctx: &Ctx<'js>,
+    ) -> javy_plugin_api::javy::quickjs::Result<Option<Constructor<'js>>> {
+        Ok(None)
+    }
+}
+
+/// Resolve an index that counts from the end when negative, clamped to `0..=len`
+fn relative_index(index: f64, len: usize) -> usize {
+    let index = index.trunc();
+    if index.is_nan() {
+        0
+    } else if index < 0.0 {
+        (len as f64 + index).max(0.0) as usize
+    } else {
+        index.min(len as f64) as usize
+    }
+}
+
+/// Get the blob data behind the native handle passed as the first argument
+fn handle_arg(args: &[Value<'_>], for_func: &str) -> Result<BlobData> {
+    let [handle, ..] = args else {
+        bail!("{} expects a blob handle, Got: 0 parameters.", for_func);
+    };
+    handle
+        .as_object()
+        .and_then(BlobData::from_object)
+        .ok_or_else(|| anyhow!("{}: invalid blob handle", for_func))
+}
+
+/// Create a blob from an array of parts.
+/// Parts can be strings (encoded as UTF-8), ArrayBuffers, typed arrays, DataViews or blob handles.
+pub fn bless_blob_create(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let mut bytes = Vec::new();
+
+    if let Some(parts) = args.first().filter(|v| !v.is_undefined() && !v.is_null()) {
+        let parts = parts
+            .as_array()
+            .ok_or_else(|| anyhow!("blob parts must be an array"))?;
+        for part in parts.iter::<Value>() {
+            let part = part?;
+            if let Some(s) = part.as_string() {
+                let s = s
+                    .to_string()
+                    .map_err(|_| anyhow!("invalid UTF-8 in blob part"))?;
+                bytes.extend_from_slice(s.as_bytes());
+            } else if let Some(obj) = part.as_object() {
+                if let Some(blob) = BlobData::from_object(obj) {
+                    bytes.extend_from_slice(blob.bytes());
+                } else if let Some(data) = read_binary_data(obj)? {
+                    bytes.extend_from_slice(&data);
+                } else {
+                    bail!("unsupported blob part");
+                }
+            } else {
+                bail!("unsupported blob part");
+            }
+        }
+    }
+
+    BlobData::new(Rc::from(bytes)).into_js(cx)
+}
+
+/// Create a blob sharing the bytes `start..end` of another blob, with the indexes `Blob.slice`
+/// takes: negative ones count from the end, and a missing end is the end of the blob.
+pub fn bless_blob_slice(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let blob = handle_arg(&args, "blob_slice")?;
+    let start = args.get(1).and_then(Value::as_number);
+    let end = args.get(2).and_then(Value::as_number);
+    blob.slice_relative(start, end).into_js(cx)
+}
+
+/// Get the size of a blob in bytes.
+pub fn bless_blob_size(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let blob = handle_arg(&args, "blob_size")?;
+    Ok(Value::new_number(cx, blob.len() as f64))
+}
+
+/// Copy the contents of a blob into a new Uint8Array.
+pub fn bless_blob_read(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let blob = handle_arg(&args, "blob_read")?;
+    Ok(TypedArray::<u8>::new_copy(cx, blob.bytes())?.into_value())
+}
+
+/// Decode the contents of a blob as UTF-8, replacing invalid sequences.
+pub fn bless_blob_text(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let blob = handle_arg(&args, "blob_text")?;
+    let text = String::from_utf8_lossy(blob.bytes());
+    Ok(Value::from_string(JSString::from_str(cx, &text)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob(bytes: &[u8]) -> BlobData {
+        BlobData::new(Rc::from(bytes))
+    }
+
+    #[test]
+    fn slices_share_the_bytes_of_their_blob() {
+        let data = blob(b"hello world");
+        let slice = data.slice(6, 11);
+        assert_eq!(slice.bytes(), b"world");
+        assert_eq!(slice.len(), 5);
+        assert!(Rc::ptr_eq(&data.bytes, &slice.bytes));
+
+        let nested = slice.slice(1, 3);
+        assert_eq!(nested.bytes(), b"or");
+        assert_eq!(nested.len(), 2);
+        assert_eq!(data.len(), 11);
+    }
+
+    #[test]
+    fn out_of_range_indexes_are_clamped() {
+        let data = blob(b"hello world").slice(6, 11);
+        assert_eq!(data.slice(2, 100).bytes(), b"rld");
+        assert_eq!(data.slice(100, 200).bytes(), b"");
+        assert_eq!(data.slice(4, 2).len(), 0);
+        assert_eq!(data.slice_relative(Some(1.0), Some(1e20)).bytes(), b"orld");
+        assert_eq!(data.slice_relative(Some(f64::INFINITY), None).len(), 0);
+        assert_eq!(data.slice_relative(Some(3.0), Some(1.0)).len(), 0);
+    }
+
+    #[test]
+    fn negative_indexes_count_from_the_end() {
+        let data = blob(b"hello world");
+        assert_eq!(data.slice_relative(Some(-5.0), None).bytes(), b"world");
+        assert_eq!(data.slice_relative(Some(0.0), Some(-6.0)).bytes(), b"hello");
+        assert_eq!(data.slice_relative(Some(-3.0), Some(-1.0)).bytes(), b"rl");
+        assert_eq!(data.slice_relative(Some(-100.0), Some(2.0)).bytes(), b"he");
+        assert_eq!(
+            data.slice_relative(Some(f64::NEG_INFINITY), Some(-100.0))
+                .len(),
+            0
+        );
+        let world = data.slice_relative(Some(-5.0), None);
+        assert_eq!(world.slice_relative(Some(-2.0), None).bytes(), b"ld");
+    }
+
+    #[test]
+    fn missing_and_fractional_indexes() {
+        let data = blob(b"hello");
+        assert_eq!(data.slice_relative(None, None).bytes(), b"hello");
+        assert_eq!(data.slice_relative(Some(1.9), Some(3.2)).bytes(), b"el");
+        assert_eq!(data.slice_relative(Some(-1.5), None).bytes(), b"o");
+        assert_eq!(data.slice_relative(Some(f64::NAN), Some(f64::NAN)).len(), 0);
+        assert_eq!(blob(b"").slice_relative(Some(-1.0), Some(1.0)).len(), 0);
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::Result;
+use blockless_sdk::http::HttpResponse;
+
+use super::{
+    decode::ContentDecoder,
+    error::{FetchError, FetchErrorKind},
+    headers::{remove_headers, HeaderList},
+    send_with_retry, FetchOptions,
+};
+
+/// Reads a response body chunk by chunk.
+///
+/// The host returns whole responses, so by default the body arrives with a single request and
+/// is read as one chunk. When the caller sets the `chunkSize` option, GET requests ask for the
+/// first `chunk_size` bytes with a `Range` header instead. When the server answers with
+/// `206 Partial Content`, the rest of the body is fetched on demand with further range requests
+/// as the stream is read. Servers that ignore the range send the whole body, which becomes a
+/// single chunk.
+pub struct BodyReader {
+    /// Bytes received with the response that have not been read yet
+    pending: Option<Vec<u8>>,
+    /// The range request for the next chunk, `None` once the whole body was received
+    next: Option<RangeRequest>,
+    /// Decoder for a compressed body that is streamed, `None` once it is finished
+    decoder: Option<ContentDecoder>,
+}
+
+/// State needed to request the remaining chunks of a body
+struct RangeRequest {
+    url: String,
+    headers: HeaderList,
+    options: FetchOptions,
+    offset: u64,
+    total: Option<u64>,
+    chunk_size: u64,
+    /// `ETag` or `Last-Modified` of the first response, so later chunks come from the same resource
+    validator: Option<String>,
+}
+
+/// Request details kept from a ranged request, to fetch the following chunks
+pub struct ChunkedRequest {
+    pub url: String,
+    pub headers: HeaderList,
+    pub options: FetchOptions,
+    pub chunk_size: u64,
+}
+
+impl BodyReader {
+    /// Create a reader for a response that was received in full
+    pub fn complete(body: Vec<u8>) -> Self {
+        Self {
+            pending: Some(body),
+            next: None,
+            decoder: None,
+        }
+    }
+
+    /// Create a reader for a response to a request that asked for the first chunk of the body.
+    /// Partial responses are presented as a `200 OK` with the headers of the full resource.
+    pub fn start(mut response: HttpResponse, request: ChunkedRequest) -> (HttpResponse, Self) {
+        let content_range = (response.status == 206)
+            .then(|| response_header(&response, "content-range"))
+            .flatten()
+            .and_then(|value| parse_content_range(&value));
+        let Some((_, end, total)) = content_range.filter(|(start, ..)| *start == 0) else {
+            let body = std::mem::take(&mut response.body);
+            return (response, Self::complete(body));
+        };
+
+        let validator = response_header(&response, "etag")
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| response_header(&response, "last-modified"));
+
+        response.status = 200;
+        response.headers.retain(|key, _| {
+            !key.eq_ignore_ascii_case("content-range")
+                && !key.eq_ignore_ascii_case("content-length")
+        });
+        if let Some(total) = total {
+            response
+                .headers
+                .insert("content-length".to_string(), total.to_string());
+        }
+
+        let offset = end + 1;
+        let done = response.body.is_empty() || total.is_some_and(|total| offset >= total);
+        let next = (!done).then(|| {
+            let mut headers = request.headers;
+            remove_headers(&mut headers, &["range", "if-range"]);
+            RangeRequest {
+                url: request.url,
+                headers,
+                options: request.options,
+                offset,
+                total,
+                chunk_size: request.chunk_size,
+                validator,
+            }
+        });
+        let body = std::mem::take(&mut response.body);
+        (
+            response,
+            Self {
+                pending: Some(body),
+                next,
+                decoder: None,
+            },
+        )
+    }
+
+    /// The whole body, if it was received with the response and nothing was read yet
+    pub fn complete_body(&self) -> Option<&[u8]> {
+        self.next.as_ref().map_or(self.pending.as_deref(), |_| None)
+    }
+
+    /// Decode the body as the response's `Content-Encoding` says, removing the coding headers.
+    /// When the whole body was received it is decoded right away and `Content-Length` becomes
+    /// the decoded length, otherwise chunks are decoded as they are read.
+    pub fn decode(mut self, response: &mut HttpResponse) -> Result<Self> {
+        let body_received = self.next.is_none();
+        if body_received && self.pending.as_ref().is_none_or(Vec::is_empty) {
+            // Bodiless responses, such as to HEAD requests, have nothing to decode
+            return Ok(self);
+        }
+        let Some(mut decoder) = ContentDecoder::for_response(&response.headers) else {
+            return Ok(self);
+        };
+
+        response.headers.retain(|key, _| {
+            !key.eq_ignore_ascii_case("content-encoding")
+                && !key.eq_ignore_ascii_case("content-length")
+        });
+        if body_received {
+            let mut body = decoder.write(&self.pending.take().unwrap_or_default())?;
+            body.extend(decoder.finish()?);
+            response
+                .headers
+                .insert("content-length".to_string(), body.len().to_string());
+            self.pending = Some(body);
+        } else {
+            self.decoder = Some(decoder);
+        }
+        Ok(self)
+    }
+
+    /// Read the next chunk of the body, decoded, `None` once the body has been read completely
+    pub fn read_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            let chunk = self.read_encoded_chunk()?;
+            let Some(decoder) = self.decoder.as_mut() else {
+                return Ok(chunk);
+            };
+            let decoded = match chunk {
+                Some(chunk) => decoder.write(&chunk)?,
+                None => self
+                    .decoder
+                    .take()
+                    .map(ContentDecoder::finish)
+                    .transpose()?
+                    .unwrap_or_default(),
+            };
+            if !decoded.is_empty() {
+                return Ok(Some(decoded));
+            }
+            if self.decoder.is_none() {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Read the next chunk of the body as it was received
+    fn read_encoded_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        if let Some(chunk) = self.pending.take().filter(|chunk| !chunk.is_empty()) {
+            return Ok(Some(chunk));
+        }
+        let Some(range) = self.next.as_mut() else {
+            return Ok(None);
+        };
+
+        let mut headers = range.headers.clone();
+        let last = range.offset + range.chunk_size - 1;
+        headers.push((
+            "range".to_string(),
+            format!("bytes={}-{}", range.offset, last),
+        ));
+        if let Some(validator) = &range.validator {
+            headers.push(("if-range".to_string(), validator.clone()));
+        }
+
+        let response = send_with_retry(&range.url, "GET", &headers, None, &range.options)?;
+        match response.status {
+            206 => {}
+            // Nothing left past the end of the resource
+            416 => {
+                self.next = None;
+                return Ok(None);
+            }
+            status => {
+                return Err(FetchError::new(
+                    FetchErrorKind::InvalidResponse,
+                    "ERR_BODY_CHANGED",
+                    format!(
+                        "Failed to read response body: the resource changed while it was being read (status {})",
+                        status
+                    ),
+                )
+                .into())
+            }
+        }
+
+        let (start, end, _) = response_header(&response, "content-range")
+            .and_then(|value| parse_content_range(&value))
+            .ok_or_else(|| invalid_range("Failed to read response body: invalid Content-Range"))?;
+        if start != range.offset {
+            return Err(invalid_range(
+                "Failed to read response body: server returned an unexpected range",
+            )
+            .into());
+        }
+
+        range.offset = end + 1;
+        if response.body.is_empty() || range.total.is_some_and(|total| range.offset >= total) {
+            self.next = None;
+        }
+        Ok(Some(response.body))
+    }
+
+    /// Stop reading, dropping any bytes that were not read yet
+    pub fn cancel(&mut self) {
+        self.pending = None;
+        self.next = None;
+        self.decoder = None;
+    }
+}
+
+fn invalid_range(message: &str) -> FetchError {
+    FetchError::new(
+        FetchErrorKind::InvalidResponse,
+        "ERR_INVALID_RESPONSE",
+        message,
+    )
+}
+
+fn response_header(response: &HttpResponse, name: &str) -> Option<String> {
+    super::header_value(&response.headers, name)
+}
+
+/// Parse a `Content-Range: bytes start-end/total` header, `total` may be `*`
+fn parse_content_range(value: &str) -> Option<(u64, u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes")?.trim_start();
+    let (range, total) = range.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    (start <= end).then_some((start, end, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &[u8]) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+            body: body.to_vec(),
+            url: String::new(),
+        }
+    }
+
+    fn response_with_range(content_range: &str) -> HttpResponse {
+        let (start, end, _) = parse_content_range(content_range).unwrap();
+        let body: Vec<u8> = (start..=end).map(|i| b'0' + i as u8).collect();
+        response(206, &[("Content-Range", content_range)], &body)
+    }
+
+    fn chunked_request() -> ChunkedRequest {
+        ChunkedRequest {
+            url: "https://example.com/file".to_string(),
+            headers: vec![("range".to_string(), "bytes=0-3".to_string())],
+            options: FetchOptions::default(),
+            chunk_size: 4,
+        }
+    }
+
+    #[test]
+    fn content_ranges_are_parsed() {
+        assert_eq!(parse_content_range("bytes 0-3/10"), Some((0, 3, Some(10))));
+        assert_eq!(parse_content_range(" bytes  4-7 / * "), Some((4, 7, None)));
+        assert_eq!(parse_content_range("bytes 7-4/10"), None);
+        assert_eq!(parse_content_range("bytes */10"), None);
+        assert_eq!(parse_content_range("items 0-3/10"), None);
+        assert_eq!(parse_content_range("bytes 0-3"), None);
+    }
+
+    #[test]
+    fn partial_responses_look_like_the_full_resource() {
+        let partial = response(
+            206,
+            &[("Content-Range", "bytes 0-3/10"), ("Content-Length", "4")],
+            b"0123",
+        );
+        let (response, reader) = BodyReader::start(partial, chunked_request());
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.headers.get("content-length").map(String::as_str),
+            Some("10")
+        );
+        assert!(!response.headers.contains_key("Content-Range"));
+        assert_eq!(reader.complete_body(), None);
+
+        let range = reader.next.as_ref().unwrap();
+        assert_eq!((range.offset, range.total), (4, Some(10)));
+        assert!(range.headers.is_empty());
+        assert_eq!(reader.pending, Some(b"0123".to_vec()));
+    }
+
+    #[test]
+    fn whole_bodies_are_a_single_chunk() {
+        let full = response(200, &[], b"0123456789");
+        let (response, reader) = BodyReader::start(full, chunked_request());
+        assert_eq!(response.status, 200);
+        assert_eq!(reader.complete_body(), Some(&b"0123456789"[..]));
+
+        // A range that does not start the body cannot be presented as the whole resource
+        let offset = response_with_range("bytes 2-5/10");
+        let (response, reader) = BodyReader::start(offset, chunked_request());
+        assert_eq!(response.status, 206);
+        assert_eq!(reader.complete_body(), Some(&b"2345"[..]));
+
+        // Nothing is left to request when the first chunk was the whole resource
+        let (_, reader) = BodyReader::start(response_with_range("bytes 0-3/4"), chunked_request());
+        assert!(reader.next.is_none());
+    }
+}
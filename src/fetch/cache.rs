@@ -0,0 +1,675 @@
+use anyhow::{anyhow, Result};
+use blockless_sdk::http::HttpResponse;
+use javy_plugin_api::javy::{quickjs::Value, Args};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    header_value,
+    headers::{has_header, parse_http_date, request_header, HeaderList},
+};
+
+/// Statuses that may be stored without explicit freshness information
+const HEURISTICALLY_CACHEABLE: [u16; 11] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Headers of a `304 Not Modified` response that do not replace the stored ones
+const NOT_UPDATED_HEADERS: [&str; 3] = ["content-length", "content-encoding", "content-range"];
+
+/// Request headers carrying credentials. Responses to requests with them are neither
+/// stored nor served from the cache, which has no way to tell whose they are.
+const CREDENTIAL_HEADERS: [&str; 3] = ["authorization", "cookie", "proxy-authorization"];
+
+/// Most responses kept in memory, the least recently used are evicted beyond it
+const MAX_ENTRIES: usize = 256;
+
+/// Most body bytes kept in memory, the least recently used responses are evicted beyond it
+const MAX_BYTES: usize = 32 * 1024 * 1024;
+
+/// How a request uses the HTTP cache, from the `cache` request option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Use fresh responses, revalidate stale ones and store new ones
+    Default,
+    /// Neither use nor update the cache
+    NoStore,
+    /// Ignore stored responses but store the new one
+    Reload,
+    /// Revalidate stored responses even when they are fresh
+    NoCache,
+    /// Use stored responses however old they are, go to the network otherwise
+    ForceCache,
+    /// Use stored responses however old they are, fail otherwise
+    OnlyIfCached,
+}
+
+impl CacheMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "default" => Some(Self::Default),
+            "no-store" => Some(Self::NoStore),
+            "reload" => Some(Self::Reload),
+            "no-cache" => Some(Self::NoCache),
+            "force-cache" => Some(Self::ForceCache),
+            "only-if-cached" => Some(Self::OnlyIfCached),
+            _ => None,
+        }
+    }
+
+    /// The mode used for a request. The cache only holds full `GET` responses to requests
+    /// without credentials, so other requests and requests the caller made ranged or
+    /// conditional bypass it.
+    pub fn for_request(self, method: &str, headers: &[(String, String)]) -> Self {
+        let conditional = [
+            "if-modified-since",
+            "if-none-match",
+            "if-unmodified-since",
+            "if-match",
+            "if-range",
+        ]
+        .iter()
+        .any(|name| has_header(headers, name));
+        let credentials = CREDENTIAL_HEADERS
+            .iter()
+            .any(|name| has_header(headers, name));
+        if method != "GET"
+            || credentials
+            || has_header(headers, "range")
+            || (self == Self::Default && conditional)
+        {
+            Self::NoStore
+        } else {
+            self
+        }
+    }
+
+    /// `Cache-Control` and `Pragma` headers telling intermediate caches how to answer
+    pub fn request_headers(self, headers: &[(String, String)]) -> HeaderList {
+        if has_header(headers, "cache-control") {
+            return Vec::new();
+        }
+        let no_cache = || ("cache-control".to_string(), "no-cache".to_string());
+        match self {
+            Self::NoCache => vec![("cache-control".to_string(), "max-age=0".to_string())],
+            Self::NoStore | Self::Reload if has_header(headers, "pragma") => vec![no_cache()],
+            Self::NoStore | Self::Reload => {
+                vec![("pragma".to_string(), "no-cache".to_string()), no_cache()]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A response stored in the HTTP cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    #[serde(skip)]
+    pub body: Vec<u8>,
+    pub url: String,
+    pub redirected: bool,
+    /// Values of the request headers named by the response's `Vary` header
+    vary: Vec<(String, Option<String>)>,
+    /// When the response was received, in milliseconds since the Unix epoch
+    response_time: i64,
+    /// Age of the response when it was received, in milliseconds
+    initial_age: i64,
+}
+
+impl CachedResponse {
+    fn new(
+        response: &HttpResponse,
+        body: &[u8],
+        url: &str,
+        request_headers: &[(String, String)],
+        redirected: bool,
+    ) -> Self {
+        let vary = header_value(&response.headers, "vary")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let value = request_header(request_headers, &name);
+                (name, value)
+            })
+            .collect();
+        let mut cached = Self {
+            status: response.status,
+            headers: response.headers.clone(),
+            body: body.to_vec(),
+            url: url.to_string(),
+            redirected,
+            vary,
+            response_time: now_ms(),
+            initial_age: 0,
+        };
+        cached.initial_age = cached.received_age();
+        cached
+    }
+
+    /// Age when received, the larger of the `Age` header and the time since `Date`
+    fn received_age(&self) -> i64 {
+        let age = self
+            .header("age")
+            .and_then(|age| age.trim().parse::<i64>().ok())
+            .unwrap_or(0)
+            * 1000;
+        let apparent = self
+            .date()
+            .map_or(0, |date| (self.response_time - date).max(0));
+        age.max(apparent)
+    }
+
+    fn header(&self, name: &str) -> Option<String> {
+        header_value(&self.headers, name)
+    }
+
+    fn date(&self) -> Option<i64> {
+        self.header("date").and_then(|date| parse_http_date(&date))
+    }
+
+    fn cache_control(&self) -> Vec<(String, Option<String>)> {
+        parse_cache_control(&self.header("cache-control").unwrap_or_default())
+    }
+
+    /// How long the response stays fresh, in milliseconds
+    fn freshness_lifetime(&self) -> i64 {
+        let directives = self.cache_control();
+        let directive = |name: &str| directives.iter().find(|(key, _)| key == name);
+        if directive("no-cache").is_some() {
+            return 0;
+        }
+        if let Some((_, Some(max_age))) = directive("max-age") {
+            return max_age.parse::<i64>().map_or(0, |seconds| seconds * 1000);
+        }
+        if let Some(expires) = self.header("expires") {
+            // Invalid dates, such as `0`, mean the response is already stale
+            return parse_http_date(&expires).map_or(0, |expires| {
+                expires - self.date().unwrap_or(self.response_time)
+            });
+        }
+        // Without explicit freshness, responses that were last modified long ago stay fresh longer
+        match (self.header("last-modified"), self.date()) {
+            (Some(modified), Some(date)) if HEURISTICALLY_CACHEABLE.contains(&self.status) => {
+                parse_http_date(&modified).map_or(0, |modified| (date - modified).max(0) / 10)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Current age of the response, in milliseconds
+    fn age(&self) -> i64 {
+        self.initial_age + (now_ms() - self.response_time).max(0)
+    }
+
+    /// Whether the response can be used without asking the server
+    pub fn is_fresh(&self) -> bool {
+        self.freshness_lifetime() > self.age()
+    }
+
+    /// Whether the response can be revalidated with a conditional request
+    pub fn has_validators(&self) -> bool {
+        self.header("etag").is_some() || self.header("last-modified").is_some()
+    }
+
+    /// `If-None-Match` and `If-Modified-Since` headers to revalidate the response
+    pub fn conditional_headers(&self) -> HeaderList {
+        let mut headers = Vec::new();
+        if let Some(etag) = self.header("etag") {
+            headers.push(("if-none-match".to_string(), etag));
+        }
+        if let Some(modified) = self.header("last-modified") {
+            headers.push(("if-modified-since".to_string(), modified));
+        }
+        headers
+    }
+
+    /// The stored response, as if it was received again
+    pub fn to_response(&self) -> HttpResponse {
+        let mut headers = self.headers.clone();
+        headers.retain(|key, _| !key.eq_ignore_ascii_case("age"));
+        headers.insert("age".to_string(), (self.age() / 1000).to_string());
+        HttpResponse {
+            status: self.status,
+            headers,
+            body: self.body.clone(),
+            url: self.url.clone(),
+        }
+    }
+
+    fn matches(&self, request_headers: &[(String, String)]) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| request_header(request_headers, name) == *value)
+    }
+}
+
+/// Responses stored for the lifetime of the module instance, and optionally on disk.
+/// Memory holds at most `MAX_ENTRIES` responses and `MAX_BYTES` of bodies, evicting the
+/// least recently used. Evicted responses that were persisted are read back from disk.
+#[derive(Default)]
+struct HttpCache {
+    entries: HashMap<String, CacheSlot>,
+    /// Body bytes of the entries in memory
+    bytes: usize,
+    /// Incremented on every use, to order the entries by recency
+    clock: u64,
+    /// Directory the entries are also written to, in a WASI preopened directory
+    directory: Option<PathBuf>,
+}
+
+struct CacheSlot {
+    entry: CachedResponse,
+    last_used: u64,
+}
+
+thread_local! {
+    static CACHE: RefCell<HttpCache> = RefCell::default();
+}
+
+impl HttpCache {
+    fn get(&mut self, url: &str) -> Option<&CachedResponse> {
+        if !self.entries.contains_key(url) {
+            let entry = self
+                .directory
+                .as_deref()
+                .and_then(|dir| read_entry(dir, url))?;
+            self.keep(url, entry);
+        }
+        self.clock += 1;
+        let slot = self.entries.get_mut(url)?;
+        slot.last_used = self.clock;
+        Some(&slot.entry)
+    }
+
+    fn insert(&mut self, url: &str, entry: CachedResponse) {
+        if let Some(dir) = &self.directory {
+            // Persistence is best effort, the in-memory entry is still used if it fails
+            let _ = write_entry(dir, url, &entry);
+        }
+        self.keep(url, entry);
+    }
+
+    fn remove(&mut self, url: &str) {
+        if let Some(dir) = &self.directory {
+            let path = entry_path(dir, url);
+            let _ = fs::remove_file(path.with_extension("json"));
+            let _ = fs::remove_file(path.with_extension("body"));
+        }
+        self.forget(url);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    /// Hold an entry in memory, evicting the least recently used ones to stay within the limits
+    fn keep(&mut self, url: &str, entry: CachedResponse) {
+        self.forget(url);
+        if entry.body.len() > MAX_BYTES {
+            return;
+        }
+        while self.entries.len() >= MAX_ENTRIES || self.bytes + entry.body.len() > MAX_BYTES {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, slot)| slot.last_used)
+                .map(|(url, _)| url.clone())
+            else {
+                break;
+            };
+            self.forget(&oldest);
+        }
+        self.clock += 1;
+        self.bytes += entry.body.len();
+        self.entries.insert(
+            url.to_string(),
+            CacheSlot {
+                entry,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Drop an entry from memory only
+    fn forget(&mut self, url: &str) {
+        if let Some(slot) = self.entries.remove(url) {
+            self.bytes -= slot.entry.body.len();
+        }
+    }
+}
+
+/// Find a stored response for a request
+pub fn lookup(url: &str, request_headers: &[(String, String)]) -> Option<CachedResponse> {
+    CACHE.with_borrow_mut(|cache| {
+        cache
+            .get(url)
+            .filter(|entry| entry.matches(request_headers))
+            .cloned()
+    })
+}
+
+/// Store a response received in full, if its headers allow it.
+/// Responses are stored under the request URL, `final_url` is where redirects ended.
+pub fn store(
+    url: &str,
+    request_headers: &[(String, String)],
+    response: &HttpResponse,
+    body: &[u8],
+    final_url: &str,
+    redirected: bool,
+) {
+    let entry = CachedResponse::new(response, body, final_url, request_headers, redirected);
+    let no_store = entry
+        .cache_control()
+        .iter()
+        .any(|(name, _)| name == "no-store");
+    let vary_any = entry.vary.iter().any(|(name, _)| name == "*");
+    if no_store || vary_any || !HEURISTICALLY_CACHEABLE.contains(&response.status) {
+        return;
+    }
+    if entry.freshness_lifetime() <= 0 && !entry.has_validators() {
+        // The response could never be used again
+        return;
+    }
+    CACHE.with_borrow_mut(|cache| cache.insert(url, entry));
+}
+
+/// Update a stored response with the headers of a `304 Not Modified` that revalidated it
+pub fn revalidated(
+    url: &str,
+    mut entry: CachedResponse,
+    response: &HttpResponse,
+) -> CachedResponse {
+    for (name, value) in &response.headers {
+        if NOT_UPDATED_HEADERS
+            .iter()
+            .any(|skip| name.eq_ignore_ascii_case(skip))
+        {
+            continue;
+        }
+        entry
+            .headers
+            .retain(|key, _| !key.eq_ignore_ascii_case(name));
+        entry.headers.insert(name.clone(), value.clone());
+    }
+    entry.response_time = now_ms();
+    entry.initial_age = entry.received_age();
+    CACHE.with_borrow_mut(|cache| cache.insert(url, entry.clone()));
+    entry
+}
+
+/// Drop the stored response for a URL, after a request that may have changed the resource
+pub fn invalidate(url: &str) {
+    CACHE.with_borrow_mut(|cache| cache.remove(url));
+}
+
+fn parse_cache_control(value: &str) -> Vec<(String, Option<String>)> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| match directive.split_once('=') {
+            Some((name, value)) => (
+                name.trim().to_ascii_lowercase(),
+                Some(value.trim().trim_matches('"').to_string()),
+            ),
+            None => (directive.to_ascii_lowercase(), None),
+        })
+        .collect()
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Path of an entry's files, without extension: entries are named after the hash of their URL
+fn entry_path(dir: &Path, url: &str) -> PathBuf {
+    let hash = Sha256::digest(url.as_bytes())
+        .iter()
+        .fold(String::new(), |mut hash, byte| {
+            let _ = write!(hash, "{:02x}", byte);
+            hash
+        });
+    dir.join(hash)
+}
+
+fn read_entry(dir: &Path, url: &str) -> Option<CachedResponse> {
+    let path = entry_path(dir, url);
+    let mut entry: CachedResponse =
+        serde_json::from_slice(&fs::read(path.with_extension("json")).ok()?).ok()?;
+    entry.body = fs::read(path.with_extension("body")).ok()?;
+    Some(entry)
+}
+
+fn write_entry(dir: &Path, url: &str, entry: &CachedResponse) -> std::io::Result<()> {
+    let path = entry_path(dir, url);
+    fs::write(path.with_extension("body"), &entry.body)?;
+    fs::write(path.with_extension("json"), serde_json::to_vec(entry)?)
+}
+
+/// Persist the HTTP cache to a directory, which must be inside a preopened WASI directory.
+/// Responses already stored there are used by later requests.
+pub fn bless_fetch_cache_persist(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let directory = args
+        .first()
+        .and_then(Value::as_string)
+        .map(|path| path.to_string())
+        .transpose()?
+        .ok_or_else(|| anyhow!("fetch_cache_persist expects a directory path"))?;
+
+    fs::create_dir_all(&directory)
+        .map_err(|e| anyhow!("Failed to use cache directory {}: {}", directory, e))?;
+    CACHE.with_borrow_mut(|cache| cache.directory = Some(PathBuf::from(directory)));
+    Ok(Value::new_undefined(cx))
+}
+
+/// Remove every stored response, from memory and from the persistence directory.
+pub fn bless_fetch_cache_clear(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, _args) = args.release();
+    CACHE.with_borrow_mut(|cache| {
+        cache.clear();
+        let Some(dir) = &cache.directory else {
+            return;
+        };
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let is_entry = path
+                .extension()
+                .is_some_and(|ext| ext == "json" || ext == "body")
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| {
+                        stem.len() == 64 && stem.bytes().all(|b| b.is_ascii_hexdigit())
+                    });
+            if is_entry {
+                let _ = fs::remove_file(path);
+            }
+        }
+    });
+    Ok(Value::new_undefined(cx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_date(ms: i64) -> String {
+        const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let (days, ms) = (ms.div_euclid(86_400_000), ms.rem_euclid(86_400_000));
+        // Civil date from days since the epoch
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        format!(
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            DAYS[days.rem_euclid(7) as usize],
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60
+        )
+    }
+
+    fn response(status: u16, headers: &[(&str, String)], body: &[u8]) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+            body: body.to_vec(),
+            url: "http://a/".to_string(),
+        }
+    }
+
+    fn entry(headers: &[(&str, String)]) -> CachedResponse {
+        CachedResponse::new(
+            &response(200, headers, b"body"),
+            b"body",
+            "http://a/",
+            &[],
+            false,
+        )
+    }
+
+    #[test]
+    fn max_age_takes_precedence_over_expires() {
+        let entry = entry(&[
+            ("Cache-Control", "public, max-age=60".to_string()),
+            ("Expires", "0".to_string()),
+        ]);
+        assert_eq!(entry.freshness_lifetime(), 60_000);
+        assert!(entry.is_fresh());
+    }
+
+    #[test]
+    fn age_counts_against_freshness() {
+        let entry = entry(&[
+            ("Cache-Control", "max-age=60".to_string()),
+            ("Age", "120".to_string()),
+        ]);
+        assert!(!entry.is_fresh());
+        let entry = entry_with_date(-30_000, "max-age=60");
+        assert!(entry.is_fresh());
+        let entry = entry_with_date(-90_000, "max-age=60");
+        assert!(!entry.is_fresh());
+    }
+
+    fn entry_with_date(offset_ms: i64, cache_control: &str) -> CachedResponse {
+        entry(&[
+            ("Cache-Control", cache_control.to_string()),
+            ("Date", http_date(now_ms() + offset_ms)),
+        ])
+    }
+
+    #[test]
+    fn invalid_expires_is_stale_and_no_cache_always_revalidates() {
+        assert!(!entry(&[("Expires", "0".to_string())]).is_fresh());
+        assert!(!entry_with_date(0, "no-cache, max-age=600").is_fresh());
+    }
+
+    #[test]
+    fn last_modified_gives_a_heuristic_lifetime() {
+        let now = now_ms();
+        let entry = entry(&[
+            ("Date", http_date(now)),
+            ("Last-Modified", http_date(now - 1_000_000_000)),
+        ]);
+        assert_eq!(entry.freshness_lifetime(), 100_000_000);
+        assert!(entry.has_validators());
+    }
+
+    #[test]
+    fn vary_headers_must_match() {
+        let entry = CachedResponse::new(
+            &response(200, &[("Vary", "Accept-Language".to_string())], b""),
+            b"",
+            "http://a/",
+            &[("accept-language".to_string(), "en".to_string())],
+            false,
+        );
+        assert!(entry.matches(&[("Accept-Language".to_string(), "en".to_string())]));
+        assert!(!entry.matches(&[("Accept-Language".to_string(), "fr".to_string())]));
+        assert!(!entry.matches(&[]));
+    }
+
+    #[test]
+    fn requests_with_credentials_bypass_the_cache() {
+        let auth = [("Authorization".to_string(), "Bearer x".to_string())];
+        assert_eq!(
+            CacheMode::Default.for_request("GET", &auth),
+            CacheMode::NoStore
+        );
+        let cookie = [("cookie".to_string(), "a=b".to_string())];
+        assert_eq!(
+            CacheMode::ForceCache.for_request("GET", &cookie),
+            CacheMode::NoStore
+        );
+        assert_eq!(
+            CacheMode::ForceCache.for_request("GET", &[]),
+            CacheMode::ForceCache
+        );
+        assert_eq!(
+            CacheMode::Default.for_request("POST", &[]),
+            CacheMode::NoStore
+        );
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let mut cache = HttpCache::default();
+        for i in 0..MAX_ENTRIES {
+            cache.insert(&format!("http://a/{}", i), entry(&[]));
+        }
+        assert!(cache.get("http://a/0").is_some());
+        cache.insert("http://a/new", entry(&[]));
+        assert_eq!(cache.entries.len(), MAX_ENTRIES);
+        assert!(cache.get("http://a/0").is_some());
+        assert!(cache.get("http://a/1").is_none());
+        assert!(cache.get("http://a/new").is_some());
+    }
+
+    #[test]
+    fn bodies_are_kept_within_the_byte_limit() {
+        let mut cache = HttpCache::default();
+        let mut large = entry(&[]);
+        large.body = vec![0; MAX_BYTES / 2 + 1];
+        cache.insert("http://a/1", large.clone());
+        cache.insert("http://a/2", large.clone());
+        assert!(cache.get("http://a/1").is_none());
+        assert_eq!(cache.bytes, MAX_BYTES / 2 + 1);
+
+        large.body = vec![0; MAX_BYTES + 1];
+        cache.insert("http://a/3", large);
+        assert!(cache.get("http://a/3").is_none());
+        cache.remove("http://a/2");
+        assert_eq!(cache.bytes, 0);
+    }
+}
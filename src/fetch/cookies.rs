@@ -0,0 +1,501 @@
+use anyhow::Result;
+use javy_plugin_api::javy::{
+    quickjs::{Array, Object, String as JSString, Value},
+    Args,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    net::IpAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+use super::headers::{split_set_cookie, unix_time_ms};
+
+/// A cookie stored from a `Set-Cookie` response header, following the RFC 6265 storage model
+#[derive(Debug, Clone)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    /// Only sent to the exact host that set it, when `Domain` was not given
+    host_only: bool,
+    path: String,
+    /// Expiry in milliseconds since the Unix epoch, `None` for session cookies
+    expires: Option<i64>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<String>,
+    /// When the cookie was first stored, to order cookies with the same path length
+    creation: i64,
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` value received from `url`, `None` if the cookie must be ignored
+    fn parse(set_cookie: &str, url: &Url, now: i64) -> Option<Self> {
+        let host = canonical_host(url)?;
+        let mut parts = set_cookie.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+            creation: now,
+        };
+        let mut max_age = None;
+        let mut expires = None;
+
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "expires" => expires = parse_cookie_date(value).or(expires),
+                "max-age" => max_age = parse_max_age(value, now).or(max_age),
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    // A cookie cannot be set for another site
+                    if !domain_matches(&host, &domain) {
+                        return None;
+                    }
+                    // Nor for a public suffix such as `com` or `co.uk`, unless that is the host
+                    // itself, in which case the cookie is only sent back to it
+                    if is_public_suffix(&domain) {
+                        if domain != host {
+                            return None;
+                        }
+                        continue;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => cookie.same_site = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        // Max-Age takes precedence over Expires
+        cookie.expires = max_age.or(expires);
+        // Secure cookies can only be set over a secure connection
+        if cookie.secure && url.scheme() != "https" {
+            return None;
+        }
+        Some(cookie)
+    }
+
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether the cookie is sent with a request to `url`
+    fn matches(&self, url: &Url, host: &str) -> bool {
+        let domain = if self.host_only {
+            self.domain == host
+        } else {
+            domain_matches(host, &self.domain)
+        };
+        domain && path_matches(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+
+    fn to_js(&self, obj: &Object<'_>) -> Result<()> {
+        let cx = obj.ctx().clone();
+        obj.set("name", JSString::from_str(cx.clone(), &self.name)?)?;
+        obj.set("value", JSString::from_str(cx.clone(), &self.value)?)?;
+        obj.set("domain", JSString::from_str(cx.clone(), &self.domain)?)?;
+        obj.set("path", JSString::from_str(cx.clone(), &self.path)?)?;
+        match self.expires {
+            Some(expires) => obj.set("expires", expires as f64)?,
+            None => obj.set("expires", Value::new_null(cx.clone()))?,
+        }
+        obj.set("hostOnly", self.host_only)?;
+        obj.set("secure", self.secure)?;
+        obj.set("httpOnly", self.http_only)?;
+        match &self.same_site {
+            Some(same_site) => obj.set("sameSite", JSString::from_str(cx, same_site)?)?,
+            None => obj.set("sameSite", Value::new_null(cx))?,
+        }
+        Ok(())
+    }
+}
+
+/// Cookies kept for the lifetime of the module instance. The jar is off until a script enables it.
+#[derive(Default)]
+struct CookieJar {
+    enabled: bool,
+    cookies: Vec<Cookie>,
+}
+
+thread_local! {
+    static JAR: RefCell<CookieJar> = RefCell::default();
+}
+
+impl CookieJar {
+    fn store(&mut self, mut cookie: Cookie, now: i64) {
+        if let Some(i) = self.cookies.iter().position(|existing| {
+            existing.name == cookie.name
+                && existing.domain == cookie.domain
+                && existing.path == cookie.path
+        }) {
+            // A replaced cookie keeps its creation time
+            cookie.creation = self.cookies.remove(i).creation;
+        }
+        if !cookie.is_expired(now) {
+            self.cookies.push(cookie);
+        }
+    }
+
+    /// Cookies sent with a request to `url`, longest paths first, then oldest first
+    fn matching(&mut self, url: &Url, now: i64) -> Vec<&Cookie> {
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+        let Some(host) = canonical_host(url) else {
+            return Vec::new();
+        };
+        let mut cookies: Vec<&Cookie> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url, &host))
+            .collect();
+        cookies.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.creation.cmp(&b.creation))
+        });
+        cookies
+    }
+}
+
+/// The `Cookie` header for a request to `url`, if the jar is enabled and has cookies for it
+pub fn cookie_header(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    JAR.with_borrow_mut(|jar| {
+        if !jar.enabled {
+            return None;
+        }
+        let cookies = jar.matching(&url, now_ms());
+        (!cookies.is_empty()).then(|| {
+            cookies
+                .iter()
+                .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    })
+}
+
+/// Store the cookies set by a response from `url`, if the jar is enabled
+pub fn store_response_cookies(url: &str, headers: &HashMap<String, String>) {
+    let Ok(url) = Url::parse(url) else {
+        return;
+    };
+    JAR.with_borrow_mut(|jar| {
+        if !jar.enabled {
+            return;
+        }
+        let now = now_ms();
+        let set_cookies = headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .flat_map(|(_, value)| split_set_cookie(value));
+        for set_cookie in set_cookies {
+            if let Some(cookie) = Cookie::parse(&set_cookie, &url, now) {
+                jar.store(cookie, now);
+            }
+        }
+    });
+}
+
+/// Lowercase host of a URL, the name cookies are matched against
+fn canonical_host(url: &Url) -> Option<String> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some(
+        host.strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .map_or(host.clone(), str::to_string),
+    )
+}
+
+/// Parse a `Max-Age` value into an expiry. Zero and negative values, however large, mean the
+/// cookie has already expired, positive values too large to represent never expire in practice.
+fn parse_max_age(value: &str, now: i64) -> Option<i64> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if negative || digits.bytes().all(|b| b == b'0') {
+        return Some(i64::MIN);
+    }
+    let seconds = digits.parse::<i64>().unwrap_or(i64::MAX);
+    Some(now.saturating_add(seconds.saturating_mul(1000)))
+}
+
+/// Whether a domain is a public suffix, under which anyone can register names, according to
+/// the Public Suffix List. Names the list does not know have their last label as suffix.
+fn is_public_suffix(domain: &str) -> bool {
+    psl::suffix_str(domain) == Some(domain)
+}
+
+/// RFC 6265 domain matching: the host is the domain or a subdomain of it, and not an IP address
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<IpAddr>().is_err())
+}
+
+/// RFC 6265 path matching: the request path is the cookie path or below it
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// The directory of the request path, used when a cookie has no `Path`
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(end) => path[..end].to_string(),
+    }
+}
+
+/// Parse a cookie date with the lenient RFC 6265 algorithm, which accepts the many formats
+/// servers send (`Wed, 21 Oct 2015 07:28:00 GMT`, `Wed, 21-Oct-15 07:28:00 GMT`, ...)
+fn parse_cookie_date(value: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let is_delimiter = |c: char| {
+        c == '\t'
+            || (' '..='/').contains(&c)
+            || (';'..='@').contains(&c)
+            || ('['..='`').contains(&c)
+            || ('{'..='~').contains(&c)
+    };
+
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in value.split(is_delimiter).filter(|token| !token.is_empty()) {
+        let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+        if time.is_none() {
+            let fields: Vec<Option<i64>> = token
+                .split(':')
+                .map(|field| {
+                    (1..=2)
+                        .contains(&field.len())
+                        .then(|| field.parse().ok())
+                        .flatten()
+                })
+                .collect();
+            if let [Some(hours), Some(minutes), Some(seconds)] = fields[..] {
+                time = Some((hours, minutes, seconds));
+                continue;
+            }
+        }
+        if day.is_none() && (1..=2).contains(&digits) {
+            day = token[..digits].parse::<i64>().ok();
+            if day.is_some() {
+                continue;
+            }
+        }
+        if month.is_none() {
+            let prefix = token.get(..3).unwrap_or_default().to_ascii_lowercase();
+            if let Some(i) = MONTHS.iter().position(|m| *m == prefix) {
+                month = Some(i as i64 + 1);
+                continue;
+            }
+        }
+        if year.is_none() && (2..=4).contains(&digits) {
+            year = token[..digits].parse::<i64>().ok();
+        }
+    }
+
+    let (hours, minutes, seconds) = time?;
+    let (day, month) = (day?, month?);
+    let year = match year? {
+        year @ 70..=99 => year + 1900,
+        year @ 0..=69 => year + 2000,
+        year => year,
+    };
+    if !(1..=31).contains(&day) || year < 1601 || hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    Some(unix_time_ms(year, month, day, (hours, minutes, seconds)))
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Turn the cookie jar on or off. Stored cookies are kept while it is off.
+pub fn bless_cookie_jar_enable(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let enabled = args.first().and_then(Value::as_bool).unwrap_or(true);
+    JAR.with_borrow_mut(|jar| jar.enabled = enabled);
+    Ok(Value::new_undefined(cx))
+}
+
+/// List stored cookies as `{ name, value, domain, path, expires, hostOnly, secure, httpOnly, sameSite }`
+/// objects. Given a URL, only the cookies that would be sent to it are listed, in sending order.
+pub fn bless_cookie_jar_list(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let url = args
+        .first()
+        .and_then(Value::as_string)
+        .map(|url| url.to_string())
+        .transpose()?;
+
+    let cookies = JAR.with_borrow_mut(|jar| -> Result<Vec<Cookie>> {
+        let now = now_ms();
+        Ok(match url {
+            Some(url) => {
+                let url = Url::parse(&url).map_err(|e| anyhow::anyhow!("Invalid URL: {}", e))?;
+                jar.matching(&url, now).into_iter().cloned().collect()
+            }
+            None => {
+                jar.cookies.retain(|cookie| !cookie.is_expired(now));
+                jar.cookies.clone()
+            }
+        })
+    })?;
+
+    let array = Array::new(cx.clone())?;
+    for (i, cookie) in cookies.iter().enumerate() {
+        let obj = Object::new(cx.clone())?;
+        cookie.to_js(&obj)?;
+        array.set(i, obj)?;
+    }
+    Ok(array.into_value())
+}
+
+/// Remove stored cookies: all of them, or those of one domain and its subdomains.
+pub fn bless_cookie_jar_clear(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let domain = args
+        .first()
+        .and_then(Value::as_string)
+        .map(|domain| domain.to_string())
+        .transpose()?
+        .map(|domain| domain.trim_start_matches('.').to_ascii_lowercase());
+
+    JAR.with_borrow_mut(|jar| match domain {
+        Some(domain) => jar
+            .cookies
+            .retain(|cookie| !domain_matches(&cookie.domain, &domain)),
+        None => jar.cookies.clear(),
+    });
+    Ok(Value::new_undefined(cx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn parse(set_cookie: &str, url: &str) -> Option<Cookie> {
+        Cookie::parse(set_cookie, &Url::parse(url).unwrap(), NOW)
+    }
+
+    #[test]
+    fn cookie_dates_are_parsed_leniently() {
+        let expected = Some(1_445_412_480_000);
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), expected);
+        assert_eq!(parse_cookie_date("Wed, 21-Oct-15 07:28:00 GMT"), expected);
+        assert_eq!(
+            parse_cookie_date("Wednesday October 21 07:28:00 2015"),
+            expected
+        );
+        assert_eq!(parse_cookie_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse_cookie_date("Wed, 32 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015"), None);
+        assert_eq!(parse_cookie_date("tomorrow"), None);
+    }
+
+    #[test]
+    fn max_age_overrides_expires() {
+        let cookie = parse(
+            "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=60",
+            "https://example.com/",
+        )
+        .unwrap();
+        assert_eq!(cookie.expires, Some(NOW + 60_000));
+
+        let cookie = parse("a=1; Max-Age=99999999999999999999", "https://example.com/").unwrap();
+        assert_eq!(cookie.expires, Some(i64::MAX));
+
+        let cookie = parse("a=1; Max-Age=soon", "https://example.com/").unwrap();
+        assert_eq!(cookie.expires, None);
+    }
+
+    #[test]
+    fn zero_and_negative_max_age_expire_at_once() {
+        for max_age in ["0", "-1", "-99999999999999999999"] {
+            let cookie = parse(&format!("a=1; Max-Age={max_age}"), "https://example.com/").unwrap();
+            assert!(cookie.is_expired(NOW), "Max-Age={max_age}");
+        }
+    }
+
+    #[test]
+    fn domains_match_the_host_and_its_subdomains() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("api.example.com", "example.com"));
+        assert!(!domain_matches("badexample.com", "example.com"));
+        assert!(!domain_matches("example.com", "api.example.com"));
+        assert!(!domain_matches("10.0.0.1", "0.0.1"));
+    }
+
+    #[test]
+    fn cookies_cannot_be_set_for_other_sites_or_public_suffixes() {
+        let cookie = parse("a=1; Domain=.example.co.uk", "https://www.example.co.uk/").unwrap();
+        assert_eq!(cookie.domain, "example.co.uk");
+        assert!(!cookie.host_only);
+
+        assert!(parse("a=1; Domain=other.com", "https://example.com/").is_none());
+        assert!(parse("a=1; Domain=co.uk", "https://www.example.co.uk/").is_none());
+        assert!(parse("a=1; Domain=com", "https://example.com/").is_none());
+        assert!(parse("a=1; Domain=github.io", "https://user.github.io/").is_none());
+
+        // A public suffix setting a cookie for itself keeps it host-only
+        let cookie = parse("a=1; Domain=localhost", "http://localhost/").unwrap();
+        assert!(cookie.host_only);
+    }
+
+    #[test]
+    fn paths_match_the_cookie_path_and_below() {
+        assert!(path_matches("/docs", "/docs"));
+        assert!(path_matches("/docs/page", "/docs"));
+        assert!(path_matches("/docs/page", "/docs/"));
+        assert!(!path_matches("/docsearch", "/docs"));
+        assert_eq!(
+            default_path(&Url::parse("https://example.com/a/b").unwrap()),
+            "/a"
+        );
+        assert_eq!(
+            default_path(&Url::parse("https://example.com/a").unwrap()),
+            "/"
+        );
+    }
+}
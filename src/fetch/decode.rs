@@ -0,0 +1,261 @@
+use anyhow::Result;
+use brotli_decompressor::DecompressorWriter;
+use flate2::write::GzDecoder;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use super::error::{FetchError, FetchErrorKind};
+use crate::bounded::{BoundedOutput, Inflater};
+
+/// `Accept-Encoding` sent when the caller did not set one
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// Size of the brotli decoder's internal buffer
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// A decoder for one content coding, fed the encoded body chunk by chunk
+enum Codec {
+    Gzip(GzDecoder<BoundedOutput>),
+    /// Zlib wrapped or raw deflate data
+    Inflater(Inflater),
+    Brotli(Box<DecompressorWriter<BoundedOutput>>),
+    /// `deflate` bodies before their first bytes tell whether they have the zlib wrapper
+    /// they should have, or are raw deflate data as some servers send
+    PendingDeflate(Vec<u8>),
+}
+
+impl Codec {
+    fn new(coding: &str) -> Option<Self> {
+        match coding {
+            "gzip" | "x-gzip" => Some(Self::Gzip(GzDecoder::new(BoundedOutput::default()))),
+            "deflate" => Some(Self::PendingDeflate(Vec::new())),
+            "br" => Some(Self::Brotli(Box::new(DecompressorWriter::new(
+                BoundedOutput::default(),
+                BROTLI_BUFFER_SIZE,
+            )))),
+            _ => None,
+        }
+    }
+
+    /// Decode a chunk, returning the bytes decoded so far
+    fn write(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => {
+                decoder.write_all(input)?;
+                Ok(decoder.get_mut().take())
+            }
+            Self::Inflater(decoder) => decoder.write(input),
+            Self::Brotli(decoder) => {
+                decoder.write_all(input)?;
+                Ok(decoder.get_mut().take())
+            }
+            Self::PendingDeflate(buffer) => {
+                buffer.extend_from_slice(input);
+                if buffer.len() < 2 {
+                    return Ok(Vec::new());
+                }
+                let buffer = std::mem::take(buffer);
+                *self = Self::Inflater(Inflater::new(is_zlib_header(&buffer)));
+                self.write(&buffer)
+            }
+        }
+    }
+
+    /// Finish decoding, failing if the encoded data was cut short
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => decoder.finish().map(BoundedOutput::into_inner),
+            Self::Inflater(decoder) => decoder.finish(),
+            Self::Brotli(mut decoder) => {
+                decoder.flush()?;
+                decoder
+                    .into_inner()
+                    .map(BoundedOutput::into_inner)
+                    .map_err(|_| {
+                        io::Error::new(io::ErrorKind::UnexpectedEof, "truncated brotli stream")
+                    })
+            }
+            Self::PendingDeflate(buffer) if buffer.is_empty() => Ok(Vec::new()),
+            Self::PendingDeflate(buffer) => {
+                let mut decoder = Self::Inflater(Inflater::new(false));
+                let mut decoded = decoder.write(&buffer)?;
+                decoded.extend(decoder.finish()?);
+                Ok(decoded)
+            }
+        }
+    }
+}
+
+/// Whether data starts with a zlib header (RFC 1950): deflate compression and a valid check value
+fn is_zlib_header(bytes: &[u8]) -> bool {
+    bytes[0] & 0x0F == 8 && (u16::from(bytes[0]) << 8 | u16::from(bytes[1])) % 31 == 0
+}
+
+/// Decodes a response body as its `Content-Encoding` header says.
+/// Codings applied in turn (`gzip, br`) are undone in reverse order, and bodies decoding to
+/// more than `MAX_DECOMPRESSED_SIZE` bytes fail rather than exhaust memory.
+pub struct ContentDecoder {
+    codecs: Vec<Codec>,
+}
+
+impl ContentDecoder {
+    /// A decoder for the response, `None` when the body is not encoded or uses a coding
+    /// that is not supported, in which case it is returned as received
+    pub fn for_response(headers: &HashMap<String, String>) -> Option<Self> {
+        let encoding = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-encoding"))
+            .map(|(_, value)| value.to_ascii_lowercase())?;
+        let codecs = encoding
+            .split(',')
+            .map(str::trim)
+            .filter(|coding| !coding.is_empty() && *coding != "identity")
+            .rev()
+            .map(Codec::new)
+            .collect::<Option<Vec<_>>>()?;
+        (!codecs.is_empty()).then_some(Self { codecs })
+    }
+
+    /// Decode the next chunk of the body
+    pub fn write(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+        let mut data = chunk.to_vec();
+        for codec in &mut self.codecs {
+            data = codec.write(&data).map_err(decode_error)?;
+        }
+        Ok(data)
+    }
+
+    /// Finish decoding once the whole body was written
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let mut pending = Vec::new();
+        for mut codec in self.codecs {
+            let mut data = codec.write(&pending).map_err(decode_error)?;
+            data.extend(codec.finish().map_err(decode_error)?);
+            pending = data;
+        }
+        Ok(pending)
+    }
+}
+
+fn decode_error(error: io::Error) -> anyhow::Error {
+    FetchError::new(
+        FetchErrorKind::InvalidResponse,
+        "ERR_CONTENT_DECODING",
+        "Failed to decode the response body",
+    )
+    .with_cause(error.to_string())
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounded::MAX_DECOMPRESSED_SIZE;
+    use flate2::{
+        write::{DeflateEncoder, GzEncoder, ZlibEncoder},
+        Compression,
+    };
+
+    const TEXT: &[u8] = b"hello compressed world! hello compressed world! hello compressed world!";
+
+    /// `"hello compressed world! "` repeated 200 times, brotli compressed
+    const BROTLI: [u8; 31] = [
+        27, 191, 18, 0, 4, 60, 100, 169, 94, 206, 136, 166, 7, 217, 100, 148, 203, 100, 133, 121,
+        60, 29, 254, 242, 54, 192, 98, 10, 0, 194, 0,
+    ];
+
+    fn decoder(encoding: &str) -> Option<ContentDecoder> {
+        let headers = HashMap::from([("Content-Encoding".to_string(), encoding.to_string())]);
+        ContentDecoder::for_response(&headers)
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Decode a body fed in chunks of `chunk_size` bytes
+    fn decode(encoding: &str, body: &[u8], chunk_size: usize) -> Result<Vec<u8>> {
+        let mut decoder = decoder(encoding).unwrap();
+        let mut decoded = Vec::new();
+        for chunk in body.chunks(chunk_size) {
+            decoded.extend(decoder.write(chunk)?);
+        }
+        decoded.extend(decoder.finish()?);
+        Ok(decoded)
+    }
+
+    #[test]
+    fn identity_and_unknown_codings_are_not_decoded() {
+        assert!(decoder("identity").is_none());
+        assert!(decoder("zstd").is_none());
+        assert!(decoder("gzip, zstd").is_none());
+        assert!(ContentDecoder::for_response(&HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn gzip_bodies_are_decoded_in_any_chunk_size() {
+        let body = gzip(TEXT);
+        for chunk_size in [1, 7, body.len()] {
+            assert_eq!(decode("GZIP", &body, chunk_size).unwrap(), TEXT);
+        }
+    }
+
+    #[test]
+    fn deflate_accepts_zlib_and_raw_data() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TEXT).unwrap();
+        let zlib = zlib.finish().unwrap();
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(TEXT).unwrap();
+        let raw = raw.finish().unwrap();
+
+        for chunk_size in [1, 64] {
+            assert_eq!(decode("deflate", &zlib, chunk_size).unwrap(), TEXT);
+            assert_eq!(decode("deflate", &raw, chunk_size).unwrap(), TEXT);
+        }
+        assert_eq!(decode("deflate", &[], 1).unwrap(), b"");
+    }
+
+    #[test]
+    fn brotli_bodies_are_decoded() {
+        let expected = b"hello compressed world! ".repeat(200);
+        for chunk_size in [1, BROTLI.len()] {
+            assert_eq!(decode("br", &BROTLI, chunk_size).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn stacked_codings_are_undone_in_reverse_order() {
+        let body = gzip(&gzip(TEXT));
+        assert_eq!(decode("gzip, identity, x-gzip", &body, 5).unwrap(), TEXT);
+    }
+
+    #[test]
+    fn bodies_decoding_past_the_limit_fail() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder
+            .write_all(&vec![0; MAX_DECOMPRESSED_SIZE + 1])
+            .unwrap();
+        let body = encoder.finish().unwrap();
+        let error = decode("gzip", &body, 64 * 1024).unwrap_err();
+        assert!(error.is::<FetchError>());
+    }
+
+    #[test]
+    fn truncated_and_corrupt_bodies_fail() {
+        let body = gzip(TEXT);
+        let error = decode("gzip", &body[..body.len() - 4], 8).unwrap_err();
+        assert!(error.is::<FetchError>());
+        assert_eq!(error.to_string(), "Failed to decode the response body");
+        assert!(decode("gzip", b"not gzip at all", 4).is_err());
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TEXT).unwrap();
+        let zlib = zlib.finish().unwrap();
+        assert!(decode("deflate", &zlib[..zlib.len() - 4], 8).is_err());
+        assert!(decode("br", &BROTLI[..10], 4).is_err());
+    }
+}
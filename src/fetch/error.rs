@@ -0,0 +1,363 @@
+use blockless_sdk::{http::HttpError, rpc::RpcError};
+use javy_plugin_api::javy::quickjs::{function::Constructor, Ctx, Exception, Object, Value};
+use std::fmt;
+
+/// How a fetch failed, which decides the JavaScript error it becomes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchErrorKind {
+    /// The request could not be completed: DNS, connection or TLS failures, permission denials
+    Network,
+    /// The request could not be sent as given: invalid URL, body or redirect
+    InvalidRequest,
+    /// The host or server answered with something that is not a usable response
+    InvalidResponse,
+    Timeout,
+    Abort,
+}
+
+/// A fetch failure that is thrown to JavaScript as a structured error.
+///
+/// Network, request and response failures become a `TypeError`, as fetch specifies, with a
+/// `code` such as `ENOTFOUND` or `ECONNREFUSED` and the host's error as `cause`.
+/// Timeouts and aborts become a `TimeoutError` or `AbortError` `DOMException`.
+#[derive(Debug)]
+pub struct FetchError {
+    kind: FetchErrorKind,
+    code: &'static str,
+    message: String,
+    cause: Option<String>,
+}
+
+impl FetchError {
+    pub fn new(kind: FetchErrorKind, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            code,
+            message: message.into(),
+            cause: None,
+        }
+    }
+
+    pub fn with_cause(mut self, cause: impl Into<String>) -> Self {
+        self.cause = Some(cause.into());
+        self
+    }
+
+    pub fn aborted() -> Self {
+        Self::new(
+            FetchErrorKind::Abort,
+            "ABORT_ERR",
+            "The operation was aborted.",
+        )
+    }
+
+    pub fn timed_out() -> Self {
+        Self::new(
+            FetchErrorKind::Timeout,
+            "ETIMEDOUT",
+            "The operation timed out.",
+        )
+    }
+
+    /// Whether sending the same request again may succeed: connection failures and host timeouts
+    pub fn is_transient(&self) -> bool {
+        match self.kind {
+            FetchErrorKind::Network => {
+                !matches!(self.code, "EACCES" | "ERR_TLS_CERT" | "ERR_UNSUPPORTED")
+            }
+            FetchErrorKind::Timeout => true,
+            _ => false,
+        }
+    }
+
+    /// Classify an error returned by the host's HTTP module.
+    /// Each `HttpError` variant maps to a kind and code directly. Only failures the host
+    /// reports as free text, `RequestFailed`, fall back to reading its message.
+    pub fn from_http(url: &str, error: HttpError) -> Self {
+        let failed = format!("Failed to fetch {}", url);
+        match error {
+            HttpError::InvalidUrl => {
+                Self::new(FetchErrorKind::InvalidRequest, "ERR_INVALID_URL", failed)
+            }
+            HttpError::SerializationError => Self::new(
+                FetchErrorKind::InvalidRequest,
+                "ERR_INVALID_REQUEST",
+                failed,
+            ),
+            HttpError::JsonParseError | HttpError::Utf8Error | HttpError::EmptyResponse => {
+                Self::new(
+                    FetchErrorKind::InvalidResponse,
+                    "ERR_INVALID_RESPONSE",
+                    failed,
+                )
+            }
+            HttpError::Timeout => Self::timed_out(),
+            HttpError::NetworkError => Self::new(FetchErrorKind::Network, "ERR_NETWORK", failed),
+            HttpError::RequestFailed(ref message) => {
+                let (kind, code) = rpc_error_code(message)
+                    .map(classify_rpc_error_code)
+                    .unwrap_or_else(|| classify_host_message(message));
+                match kind {
+                    FetchErrorKind::Timeout => Self::timed_out(),
+                    kind => Self::new(kind, code, failed),
+                }
+            }
+            HttpError::RpcError(RpcError::BufferTooSmall) => Self::new(
+                FetchErrorKind::InvalidResponse,
+                "ERR_RESPONSE_TOO_LARGE",
+                format!(
+                    "{}: the response is larger than the host can return",
+                    failed
+                ),
+            ),
+            HttpError::RpcError(RpcError::MethodNotFound) => Self::new(
+                FetchErrorKind::Network,
+                "ERR_UNSUPPORTED",
+                format!("{}: the host does not provide HTTP access", failed),
+            ),
+            HttpError::RpcError(RpcError::InvalidParams) => Self::new(
+                FetchErrorKind::InvalidRequest,
+                "ERR_INVALID_REQUEST",
+                failed,
+            ),
+            HttpError::RpcError(RpcError::InvalidJson | RpcError::Utf8Error) => Self::new(
+                FetchErrorKind::InvalidResponse,
+                "ERR_INVALID_RESPONSE",
+                failed,
+            ),
+            HttpError::RpcError(RpcError::InternalError) | HttpError::Unknown(_) => {
+                Self::new(FetchErrorKind::Network, "ERR_NETWORK", failed)
+            }
+        }
+        .with_cause(error.to_string())
+    }
+
+    /// Build the JavaScript error object
+    fn to_js<'js>(&self, cx: &Ctx<'js>) -> javy_plugin_api::javy::quickjs::Result<Value<'js>> {
+        let error: Object = match self.kind {
+            FetchErrorKind::Timeout | FetchErrorKind::Abort => {
+                let name = if self.kind == FetchErrorKind::Timeout {
+                    "TimeoutError"
+                } else {
+                    "AbortError"
+                };
+                match cx.globals().get::<_, Option<Constructor>>("DOMException")? {
+                    Some(dom_exception) => {
+                        dom_exception.construct((self.message.as_str(), name))?
+                    }
+                    None => {
+                        let error = Exception::from_message(cx.clone(), &self.message)?;
+                        error.set("name", name)?;
+                        error.into_object()
+                    }
+                }
+            }
+            _ => {
+                let error: Object = cx
+                    .globals()
+                    .get::<_, Constructor>("TypeError")?
+                    .construct((self.message.as_str(),))?;
+                error.set("code", self.code)?;
+                error
+            }
+        };
+
+        if let Some(cause) = &self.cause {
+            let cause = Exception::from_message(cx.clone(), cause)?;
+            cause.set("code", self.code)?;
+            error.set("cause", cause)?;
+        }
+        Ok(error.into_value())
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Throw fetch errors as structured JavaScript errors, leaving other errors unchanged
+pub fn throw_fetch_error(cx: &Ctx<'_>, error: anyhow::Error) -> anyhow::Error {
+    match error.downcast::<FetchError>() {
+        Ok(error) => match error.to_js(cx) {
+            Ok(value) => cx.throw(value).into(),
+            Err(e) => e.into(),
+        },
+        Err(error) => error,
+    }
+}
+
+/// The code of a JSON-RPC error, which the SDK reports as `RPC error: <message> (code: <code>)`
+fn rpc_error_code(message: &str) -> Option<i32> {
+    let code = message
+        .strip_prefix("RPC error: ")?
+        .rsplit_once("(code: ")?
+        .1
+        .strip_suffix(')')?;
+    code.trim().parse().ok()
+}
+
+/// Classify a JSON-RPC error code, as defined by the JSON-RPC 2.0 specification
+fn classify_rpc_error_code(code: i32) -> (FetchErrorKind, &'static str) {
+    match code {
+        // Parse error, invalid request or invalid params: the host could not take the request
+        -32700 | -32600 | -32602 => (FetchErrorKind::InvalidRequest, "ERR_INVALID_REQUEST"),
+        // Method not found: the host does not provide HTTP access
+        -32601 => (FetchErrorKind::Network, "ERR_UNSUPPORTED"),
+        _ => (FetchErrorKind::Network, "ERR_NETWORK"),
+    }
+}
+
+/// Classify the free-text message of a failed host request.
+///
+/// This is only used for `HttpError::RequestFailed` messages without a JSON-RPC code, which
+/// carry nothing but the host's wording; every other variant is mapped directly. It looks for
+/// the wording common HTTP stacks use for each failure, so a host that words its errors
+/// differently gets the generic `ERR_NETWORK` rather than a specific code.
+fn classify_host_message(message: &str) -> (FetchErrorKind, &'static str) {
+    let message = message.to_ascii_lowercase();
+    let has = |needles: &[&str]| needles.iter().any(|needle| message.contains(needle));
+
+    if has(&["timed out", "timeout", "deadline"]) {
+        (FetchErrorKind::Timeout, "ETIMEDOUT")
+    } else if has(&[
+        "dns",
+        "resolve",
+        "lookup",
+        "name or service not known",
+        "no such host",
+        "nodename",
+    ]) {
+        (FetchErrorKind::Network, "ENOTFOUND")
+    } else if has(&["connection refused", "refused"]) {
+        (FetchErrorKind::Network, "ECONNREFUSED")
+    } else if has(&["connection reset", "reset by peer", "broken pipe"]) {
+        (FetchErrorKind::Network, "ECONNRESET")
+    } else if has(&[
+        "permission",
+        "not permitted",
+        "not allowed",
+        "denied",
+        "forbidden",
+    ]) {
+        (FetchErrorKind::Network, "EACCES")
+    } else if has(&["certificate", "tls", "ssl"]) {
+        (FetchErrorKind::Network, "ERR_TLS_CERT")
+    } else if has(&["invalid url", "relative url", "url parse", "invalid uri"]) {
+        (FetchErrorKind::InvalidRequest, "ERR_INVALID_URL")
+    } else if has(&["invalid header", "invalid method", "builder error"]) {
+        (FetchErrorKind::InvalidRequest, "ERR_INVALID_REQUEST")
+    } else {
+        (FetchErrorKind::Network, "ERR_NETWORK")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_errors_are_classified_by_code() {
+        let error = FetchError::from_http(
+            "http://a/",
+            HttpError::RequestFailed("RPC error: Method not found (code: -32601)".to_string()),
+        );
+        assert_eq!(error.kind, FetchErrorKind::Network);
+        assert_eq!(error.code, "ERR_UNSUPPORTED");
+
+        let error = FetchError::from_http(
+            "http://a/",
+            HttpError::RequestFailed("RPC error: timed out (code: -32602)".to_string()),
+        );
+        assert_eq!(error.code, "ERR_INVALID_REQUEST");
+    }
+
+    #[test]
+    fn host_messages_fall_back_to_their_wording() {
+        let error = FetchError::from_http(
+            "http://a/",
+            HttpError::RequestFailed("error trying to connect: Connection refused".to_string()),
+        );
+        assert_eq!(error.code, "ECONNREFUSED");
+        assert!(error.is_transient());
+
+        let error = FetchError::from_http(
+            "http://a/",
+            HttpError::RequestFailed("operation timed out".to_string()),
+        );
+        assert_eq!(error.kind, FetchErrorKind::Timeout);
+
+        let error = FetchError::from_http(
+            "http://a/",
+            HttpError::RequestFailed("something new".to_string()),
+        );
+        assert_eq!(error.code, "ERR_NETWORK");
+    }
+
+    #[test]
+    fn variants_are_mapped_directly() {
+        let error =
+            FetchError::from_http("http://a/", HttpError::RpcError(RpcError::BufferTooSmall));
+        assert_eq!(error.code, "ERR_RESPONSE_TOO_LARGE");
+        assert!(!error.is_transient());
+        assert_eq!(
+            FetchError::from_http("http://a/", HttpError::Timeout).kind,
+            FetchErrorKind::Timeout
+        );
+    }
+
+    #[test]
+    fn only_request_failed_messages_are_classified_by_wording() {
+        let variants = [
+            HttpError::InvalidUrl,
+            HttpError::SerializationError,
+            HttpError::JsonParseError,
+            HttpError::Utf8Error,
+            HttpError::EmptyResponse,
+            HttpError::NetworkError,
+            HttpError::Timeout,
+            HttpError::RpcError(RpcError::InvalidJson),
+            HttpError::RpcError(RpcError::MethodNotFound),
+            HttpError::RpcError(RpcError::InvalidParams),
+            HttpError::RpcError(RpcError::InternalError),
+            HttpError::RpcError(RpcError::BufferTooSmall),
+            HttpError::RpcError(RpcError::Utf8Error),
+            HttpError::Unknown(7),
+        ];
+        for error in variants {
+            // Listing every variant makes this fail to build when the SDK adds one
+            let expected = match &error {
+                HttpError::InvalidUrl => "ERR_INVALID_URL",
+                HttpError::SerializationError | HttpError::RpcError(RpcError::InvalidParams) => {
+                    "ERR_INVALID_REQUEST"
+                }
+                HttpError::JsonParseError
+                | HttpError::Utf8Error
+                | HttpError::EmptyResponse
+                | HttpError::RpcError(RpcError::InvalidJson | RpcError::Utf8Error) => {
+                    "ERR_INVALID_RESPONSE"
+                }
+                HttpError::Timeout => "ETIMEDOUT",
+                HttpError::RpcError(RpcError::MethodNotFound) => "ERR_UNSUPPORTED",
+                HttpError::RpcError(RpcError::BufferTooSmall) => "ERR_RESPONSE_TOO_LARGE",
+                HttpError::NetworkError
+                | HttpError::RpcError(RpcError::InternalError)
+                | HttpError::Unknown(_) => "ERR_NETWORK",
+                HttpError::RequestFailed(_) => unreachable!(),
+            };
+            let text = error.to_string();
+            let fetch_error = FetchError::from_http("http://a/", error);
+            assert_eq!(fetch_error.code, expected, "{}", text);
+            assert_eq!(fetch_error.cause.as_deref(), Some(text.as_str()));
+        }
+
+        // The same text sent as a message would be classified differently
+        let (_, code) = classify_host_message(&RpcError::MethodNotFound.to_string());
+        assert_eq!(code, "ERR_NETWORK");
+        let (_, code) = classify_host_message(&RpcError::BufferTooSmall.to_string());
+        assert_eq!(code, "ERR_NETWORK");
+    }
+}
@@ -0,0 +1,314 @@
+use anyhow::{anyhow, bail, Result};
+use javy_plugin_api::javy::{
+    quickjs::{
+        class::{ClassId, JsClass, Trace, Tracer, Writable},
+        function::Constructor,
+        Array, Class, Ctx, Object, String as JSString, Value,
+    },
+    Args,
+};
+
+use super::read_binary_data;
+
+/// An event parsed from a `text/event-stream` body
+pub struct ServerEvent {
+    pub event: String,
+    pub data: String,
+    pub id: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies, following the HTML event stream
+/// interpretation rules. Chunks may split lines (and `\r\n` pairs) anywhere.
+#[derive(Default)]
+pub struct EventStreamParser {
+    /// Bytes of the current, incomplete line
+    line: Vec<u8>,
+    /// Whether the start of the stream was seen, to strip a leading byte order mark
+    started: bool,
+    /// The previous chunk ended with `\r`, so a leading `\n` is part of the same line break
+    after_cr: bool,
+    event: String,
+    data: String,
+    /// Last event ID, kept across events until the stream sets a new one
+    last_event_id: String,
+    /// Reconnection time in milliseconds, if the stream set one
+    retry: Option<u64>,
+}
+
+impl EventStreamParser {
+    /// Create a parser resuming a stream, so events without an `id` field keep the last ID
+    /// received on the previous connection
+    pub fn resuming(last_event_id: String) -> Self {
+        Self {
+            last_event_id,
+            ..Self::default()
+        }
+    }
+
+    /// Feed a chunk of the body, returning the events completed by it
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Vec<ServerEvent> {
+        if !self.started {
+            self.line.extend_from_slice(chunk);
+            if self.line.len() < 3 && b"\xEF\xBB\xBF".starts_with(&self.line) {
+                // Not enough bytes yet to tell whether the stream starts with a BOM
+                return Vec::new();
+            }
+            self.started = true;
+            let mut bytes = std::mem::take(&mut self.line);
+            if bytes.starts_with(b"\xEF\xBB\xBF") {
+                bytes.drain(..3);
+            }
+            return self.feed(&bytes);
+        }
+
+        let mut events = Vec::new();
+        if self.after_cr && chunk.first() == Some(&b'\n') {
+            chunk = &chunk[1..];
+        }
+        self.after_cr = false;
+
+        let mut rest = chunk;
+        while let Some(end) = rest.iter().position(|&b| b == b'\r' || b == b'\n') {
+            self.line.extend_from_slice(&rest[..end]);
+            let line = std::mem::take(&mut self.line);
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+
+            if rest[end] == b'\r' {
+                match rest.get(end + 1) {
+                    Some(b'\n') => rest = &rest[end + 2..],
+                    Some(_) => rest = &rest[end + 1..],
+                    None => {
+                        self.after_cr = true;
+                        rest = &[];
+                    }
+                }
+            } else {
+                rest = &rest[end + 1..];
+            }
+        }
+        self.line.extend_from_slice(rest);
+        events
+    }
+
+    /// Reconnection time set by the stream, in milliseconds
+    pub fn retry(&self) -> Option<u64> {
+        self.retry
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<ServerEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // Comment
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_string(),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok().or(self.retry);
+            }
+            // Unknown fields are ignored
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<ServerEvent> {
+        let event = std::mem::take(&mut self.event);
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(ServerEvent {
+            event: if event.is_empty() {
+                "message".to_string()
+            } else {
+                event
+            },
+            data,
+            id: self.last_event_id.clone(),
+        })
+    }
+}
+
+impl<'js> Trace<'js> for EventStreamParser {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+impl<'js> JsClass<'js> for EventStreamParser {
+    const NAME: &'static str = "NativeEventStream";
+
+    type Mutable = Writable;
+
+    fn class_id() -> &'static ClassId {
+        static ID: ClassId = ClassId::new();
+        &ID
+    }
+
+    fn prototype(ctx: &Ctx<'js>) -> javy_plugin_api::javy::quickjs::Result<Option<Object<'js>>> {
+        Object::new(ctx.clone()).map(Some)
+    }
+
+    fn constructor(
+        _ctx: &Ctx<'js>,
+    ) -> javy_plugin_api::javy::quickjs::Result<Option<Constructor<'js>>> {
+        Ok(None)
+    }
+}
+
+/// Create a parser for a `text/event-stream` body.
+/// An optional last event ID, from an earlier connection to the stream, seeds the parser.
+pub fn bless_event_stream_create(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let last_event_id = match args.first().and_then(Value::as_string) {
+        Some(id) => id
+            .to_string()
+            .map_err(|_| anyhow!("invalid UTF-8 in last event ID"))?,
+        None => String::new(),
+    };
+    Ok(Class::instance(cx, EventStreamParser::resuming(last_event_id))?.into_value())
+}
+
+/// Feed a chunk (string or binary data) to a parser.
+/// Returns an array of `{ event, data, id, retry }` objects for the events completed by the chunk.
+pub fn bless_event_stream_parse(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let [handle, chunk, ..] = args.as_slice() else {
+        bail!(
+            "event_stream_parse expects 2 parameters: the parser handle and a chunk, Got: {} parameters.",
+            args.len()
+        );
+    };
+    let parser = handle
+        .as_object()
+        .and_then(Class::<EventStreamParser>::from_object)
+        .ok_or_else(|| anyhow!("event_stream_parse: invalid parser handle"))?;
+
+    let bytes = if let Some(s) = chunk.as_string() {
+        s.to_string()
+            .map_err(|_| anyhow!("invalid UTF-8 in event stream chunk"))?
+            .into_bytes()
+    } else {
+        chunk
+            .as_object()
+            .map(read_binary_data)
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| anyhow!("event stream chunks must be strings or binary data"))?
+    };
+
+    let mut parser = parser.borrow_mut();
+    let events = parser.feed(&bytes);
+    let array = Array::new(cx.clone())?;
+    for (i, event) in events.into_iter().enumerate() {
+        let obj = Object::new(cx.clone())?;
+        obj.set("event", JSString::from_str(cx.clone(), &event.event)?)?;
+        obj.set("data", JSString::from_str(cx.clone(), &event.data)?)?;
+        obj.set("id", JSString::from_str(cx.clone(), &event.id)?)?;
+        match parser.retry() {
+            Some(retry) => obj.set("retry", retry as f64)?,
+            None => obj.set("retry", Value::new_undefined(cx.clone()))?,
+        }
+        array.set(i, obj)?;
+    }
+    Ok(array.into_value())
+}
+
+/// Get the reconnection time set by the stream in milliseconds, or `null` if none was set.
+pub fn bless_event_stream_retry(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let parser = args
+        .first()
+        .and_then(Value::as_object)
+        .and_then(Class::<EventStreamParser>::from_object)
+        .ok_or_else(|| anyhow!("event_stream_retry: invalid parser handle"))?;
+    let retry = parser.borrow().retry();
+    Ok(match retry {
+        Some(retry) => Value::new_number(cx, retry as f64),
+        None => Value::new_null(cx),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&[u8]]) -> Vec<(String, String, String)> {
+        let mut parser = EventStreamParser::default();
+        chunks
+            .iter()
+            .flat_map(|chunk| parser.feed(chunk))
+            .map(|event| (event.event, event.data, event.id))
+            .collect()
+    }
+
+    fn event(event: &str, data: &str, id: &str) -> (String, String, String) {
+        (event.to_string(), data.to_string(), id.to_string())
+    }
+
+    #[test]
+    fn events_are_dispatched_on_blank_lines() {
+        let events = parse(&[b"data: one\ndata:two\n\nevent: update\ndata: three\n\ndata: open"]);
+        assert_eq!(
+            events,
+            [
+                event("message", "one\ntwo", ""),
+                event("update", "three", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_may_split_lines_anywhere() {
+        let stream = b"\xEF\xBB\xBFdata: caf\xC3\xA9\r\n\r\ndata: b\rdata: c\r\r";
+        let expected = [
+            event("message", "caf\u{e9}", ""),
+            event("message", "b\nc", ""),
+        ];
+        for chunk_size in 1..stream.len() {
+            let chunks: Vec<&[u8]> = stream.chunks(chunk_size).collect();
+            assert_eq!(parse(&chunks), expected, "chunks of {chunk_size} bytes");
+        }
+    }
+
+    #[test]
+    fn comments_unknown_fields_and_empty_events_are_ignored() {
+        let events = parse(&[b": keep-alive\nfoo: bar\nevent: ping\n\ndata\n\n"]);
+        assert_eq!(events, [event("message", "", "")]);
+    }
+
+    #[test]
+    fn the_last_event_id_is_kept_until_changed() {
+        let events = parse(&[b"id: 1\ndata: a\n\ndata: b\n\nid\ndata: c\n\nid: x\0y\ndata: d\n\n"]);
+        let ids: Vec<_> = events.into_iter().map(|(.., id)| id).collect();
+        assert_eq!(ids, ["1", "1", "", ""]);
+
+        let mut parser = EventStreamParser::resuming("41".to_string());
+        let events = parser.feed(b"data: resumed\n\nid: 42\ndata: next\n\n");
+        let ids: Vec<_> = events.into_iter().map(|event| event.id).collect();
+        assert_eq!(ids, ["41", "42"]);
+    }
+
+    #[test]
+    fn retry_only_accepts_digits() {
+        let mut parser = EventStreamParser::default();
+        parser.feed(b"retry: 1500\n");
+        assert_eq!(parser.retry(), Some(1500));
+        parser.feed(b"retry: 2s\nretry:\n");
+        assert_eq!(parser.retry(), Some(1500));
+    }
+}
@@ -1,24 +1,432 @@
 // Wrap everything in an anonymous function to avoid leaking local variables into the global scope.
 (function () {
     // Get a reference to the function before we delete it from `globalThis`.
-    const __javy_fetchio_request = globalThis.__javy_fetchio_request;
+    const __javy_fetchio_start = globalThis.__javy_fetchio_start;
+    const __javy_fetchio_complete = globalThis.__javy_fetchio_complete;
+    const __javy_bless_blob_create = globalThis.__javy_bless_blob_create;
+    const __javy_bless_blob_slice = globalThis.__javy_bless_blob_slice;
+    const __javy_bless_blob_size = globalThis.__javy_bless_blob_size;
+    const __javy_bless_blob_read = globalThis.__javy_bless_blob_read;
+    const __javy_bless_blob_text = globalThis.__javy_bless_blob_text;
+    const __javy_bless_form_data_parse = globalThis.__javy_bless_form_data_parse;
+    const __javy_bless_upload_create = globalThis.__javy_bless_upload_create;
+    const __javy_bless_upload_write = globalThis.__javy_bless_upload_write;
+    const __javy_bless_upload_from_fd = globalThis.__javy_bless_upload_from_fd;
+    const __javy_bless_event_stream_create = globalThis.__javy_bless_event_stream_create;
+    const __javy_bless_event_stream_parse = globalThis.__javy_bless_event_stream_parse;
+    const __javy_bless_event_stream_retry = globalThis.__javy_bless_event_stream_retry;
+    const __javy_bless_fetch_cache_persist = globalThis.__javy_bless_fetch_cache_persist;
+    const __javy_bless_fetch_cache_clear = globalThis.__javy_bless_fetch_cache_clear;
+    const __javy_bless_cookie_jar_enable = globalThis.__javy_bless_cookie_jar_enable;
+    const __javy_bless_cookie_jar_list = globalThis.__javy_bless_cookie_jar_list;
+    const __javy_bless_cookie_jar_clear = globalThis.__javy_bless_cookie_jar_clear;
+
+    // Minimal DOMException for environments (like QuickJS) that do not provide one.
+    const DOMException = globalThis.DOMException || class DOMException extends Error {
+        constructor(message = '', name = 'Error') {
+            super(message);
+            this._name = String(name);
+        }
+
+        get name() { return this._name; }
+
+        get code() {
+            return DOMException._codes[this._name] || 0;
+        }
+
+        static get _codes() {
+            return {
+                IndexSizeError: 1,
+                NotFoundError: 8,
+                NotSupportedError: 9,
+                InvalidStateError: 11,
+                SyntaxError: 12,
+                NetworkError: 19,
+                AbortError: 20,
+                TimeoutError: 23,
+                DataCloneError: 25,
+            };
+        }
+    };
+
+    class Event {
+        constructor(type, init = {}) {
+            this._type = String(type);
+            this._target = null;
+            this._cancelable = !!init.cancelable;
+            this._defaultPrevented = false;
+            this._stopped = false;
+        }
+
+        get type() { return this._type; }
+        get target() { return this._target; }
+        get currentTarget() { return this._target; }
+        get cancelable() { return this._cancelable; }
+        get defaultPrevented() { return this._defaultPrevented; }
+
+        preventDefault() {
+            if (this._cancelable) {
+                this._defaultPrevented = true;
+            }
+        }
+
+        stopImmediatePropagation() {
+            this._stopped = true;
+        }
+    }
+
+    class EventTarget {
+        constructor() {
+            this._listeners = {};
+        }
+
+        addEventListener(type, listener, options = {}) {
+            if (!listener) {
+                return;
+            }
+            type = String(type);
+            const once = typeof options === 'object' && !!options.once;
+            const list = this._listeners[type] || (this._listeners[type] = []);
+            if (!list.some(entry => entry.listener === listener)) {
+                list.push({ listener, once });
+            }
+        }
+
+        removeEventListener(type, listener) {
+            type = String(type);
+            const list = this._listeners[type];
+            if (list) {
+                this._listeners[type] = list.filter(entry => entry.listener !== listener);
+            }
+        }
+
+        dispatchEvent(event) {
+            event._target = this;
+            // Call the `on<type>` handler first, then registered listeners in order
+            const handler = this['on' + event.type];
+            if (typeof handler === 'function') {
+                handler.call(this, event);
+            }
+            const list = (this._listeners[event.type] || []).slice();
+            for (const entry of list) {
+                if (event._stopped) {
+                    break;
+                }
+                if (entry.once) {
+                    this.removeEventListener(event.type, entry.listener);
+                }
+                if (typeof entry.listener === 'function') {
+                    entry.listener.call(this, event);
+                } else if (entry.listener && typeof entry.listener.handleEvent === 'function') {
+                    entry.listener.handleEvent(event);
+                }
+            }
+            return !event.defaultPrevented;
+        }
+    }
+
+    class AbortSignal extends EventTarget {
+        constructor() {
+            super();
+            this._aborted = false;
+            this._reason = undefined;
+            // Absolute deadline (ms since epoch) for timeout signals. There are no timers
+            // in Javy, so deadlines are checked lazily and passed to the host as a timeout.
+            this._deadline = undefined;
+            this.onabort = null;
+        }
+
+        get aborted() {
+            this._checkDeadline();
+            return this._aborted;
+        }
+
+        get reason() {
+            this._checkDeadline();
+            return this._reason;
+        }
+
+        throwIfAborted() {
+            if (this.aborted) {
+                throw this._reason;
+            }
+        }
+
+        _checkDeadline() {
+            if (!this._aborted && this._deadline !== undefined && Date.now() >= this._deadline) {
+                this._abort(new DOMException('The operation timed out.', 'TimeoutError'));
+            }
+        }
+
+        _abort(reason) {
+            if (this._aborted) {
+                return;
+            }
+            this._aborted = true;
+            this._reason = reason !== undefined
+                ? reason
+                : new DOMException('The operation was aborted.', 'AbortError');
+            this.dispatchEvent(new Event('abort'));
+        }
+
+        static abort(reason) {
+            const signal = new AbortSignal();
+            signal._abort(reason);
+            return signal;
+        }
+
+        static timeout(milliseconds) {
+            const ms = Number(milliseconds);
+            if (!Number.isFinite(ms) || ms < 0) {
+                throw new TypeError('AbortSignal.timeout: milliseconds must be a non-negative number');
+            }
+            const signal = new AbortSignal();
+            signal._deadline = Date.now() + ms;
+            return signal;
+        }
+
+        static any(signals) {
+            const signal = new AbortSignal();
+            for (const source of signals) {
+                if (!(source instanceof AbortSignal)) {
+                    throw new TypeError('AbortSignal.any: all entries must be AbortSignal instances');
+                }
+                if (source.aborted) {
+                    signal._abort(source.reason);
+                    return signal;
+                }
+            }
+            for (const source of signals) {
+                if (source._deadline !== undefined &&
+                    (signal._deadline === undefined || source._deadline < signal._deadline)) {
+                    signal._deadline = source._deadline;
+                }
+                source.addEventListener('abort', () => signal._abort(source.reason), { once: true });
+            }
+            return signal;
+        }
+    }
+
+    class AbortController {
+        constructor() {
+            this._signal = new AbortSignal();
+        }
+
+        get signal() { return this._signal; }
+
+        abort(reason) {
+            this._signal._abort(reason);
+        }
+    }
+
+    // Provided by streams.js, evaluated before this file
+    const ReadableStream = globalThis.ReadableStream;
+
+    // Size of the chunks produced by Blob.stream()
+    const BLOB_STREAM_CHUNK_SIZE = 64 * 1024;
+
+    // Blob backed by bytes held in Rust memory; slices share the same bytes.
+    class Blob {
+        constructor(blobParts = [], options = {}) {
+            if (blobParts === null || typeof blobParts !== 'object' || typeof blobParts[Symbol.iterator] !== 'function') {
+                throw new TypeError("Failed to construct 'Blob': The provided value cannot be converted to a sequence.");
+            }
+            const parts = Array.from(blobParts, (part) => {
+                if (part instanceof Blob) {
+                    return part._handle;
+                }
+                if (part instanceof ArrayBuffer || ArrayBuffer.isView(part)) {
+                    return part;
+                }
+                return String(part);
+            });
+            this._handle = __javy_bless_blob_create(parts);
+            this._type = Blob._normalizeType(options.type);
+        }
+
+        // Types are lowercased; types with non printable ASCII characters become ''
+        static _normalizeType(type) {
+            if (type === undefined || type === null) {
+                return '';
+            }
+            type = String(type);
+            return /^[\x20-\x7E]*$/.test(type) ? type.toLowerCase() : '';
+        }
+
+        static _fromHandle(handle, type) {
+            const blob = Object.create(Blob.prototype);
+            blob._handle = handle;
+            blob._type = Blob._normalizeType(type);
+            return blob;
+        }
+
+        get size() { return __javy_bless_blob_size(this._handle); }
+        get type() { return this._type; }
+        get [Symbol.toStringTag]() { return 'Blob'; }
+
+        slice(start = 0, end = undefined, contentType = '') {
+            // Negative indexes count from the end, the native slice clamps them to the blob
+            const index = (value) => value === undefined ? undefined : Number(value);
+            return Blob._fromHandle(__javy_bless_blob_slice(this._handle, index(start), index(end)), contentType);
+        }
+
+        async text() {
+            return __javy_bless_blob_text(this._handle);
+        }
+
+        async arrayBuffer() {
+            return __javy_bless_blob_read(this._handle).buffer;
+        }
+
+        async bytes() {
+            return __javy_bless_blob_read(this._handle);
+        }
+
+        stream() {
+            const handle = this._handle;
+            const size = this.size;
+            let offset = 0;
+            return new ReadableStream({
+                pull(controller) {
+                    if (offset >= size) {
+                        controller.close();
+                        return;
+                    }
+                    const end = Math.min(offset + BLOB_STREAM_CHUNK_SIZE, size);
+                    controller.enqueue(__javy_bless_blob_read(__javy_bless_blob_slice(handle, offset, end)));
+                    offset = end;
+                },
+                cancel() {
+                    offset = size;
+                },
+            });
+        }
+    }
+
+    class File extends Blob {
+        constructor(fileBits, fileName, options = {}) {
+            if (arguments.length < 2) {
+                throw new TypeError("Failed to construct 'File': 2 arguments required, but only " + arguments.length + " present.");
+            }
+            super(fileBits, options);
+            this._name = String(fileName);
+            this._lastModified = options.lastModified === undefined ? Date.now() : Number(options.lastModified);
+        }
+
+        get name() { return this._name; }
+        get lastModified() { return this._lastModified; }
+        get webkitRelativePath() { return ''; }
+        get [Symbol.toStringTag]() { return 'File'; }
+    }
+
+    // Bodies produced chunk by chunk: ReadableStreams (ours or the host's) and async iterables
+    function isStreamBody(value) {
+        return value instanceof ReadableStream ||
+            typeof value.getReader === 'function' ||
+            typeof value[Symbol.asyncIterator] === 'function';
+    }
+
+    // Descriptors returned by wasi_fs.open
+    function isDescriptor(value) {
+        return typeof value.rawfd === 'number' && typeof value.read === 'function';
+    }
+
+    // Iterate a stream through its reader, for streams that are not async iterable
+    async function* readerChunks(reader) {
+        try {
+            for (;;) {
+                const { value, done } = await reader.read();
+                if (done) {
+                    return;
+                }
+                yield value;
+            }
+        } finally {
+            reader.releaseLock();
+        }
+    }
+
+    // Append the chunks of a streamed body to a native upload buffer, one chunk at a time
+    async function collectStreamBody(body, signal) {
+        if (body.locked || body._disturbed) {
+            throw new TypeError('Failed to execute \'fetch\': body stream is already locked or disturbed');
+        }
+        const upload = __javy_bless_upload_create();
+        const chunks = typeof body[Symbol.asyncIterator] === 'function' ? body : readerChunks(body.getReader());
+        for await (const chunk of chunks) {
+            if (signal && signal.aborted) {
+                throw signal.reason;
+            }
+            try {
+                __javy_bless_upload_write(upload, chunk);
+            } catch (error) {
+                throw new TypeError(error.message);
+            }
+        }
+        return upload;
+    }
+
+    // URLSearchParams are detected by tag so any implementation can be used as a body
+    function isURLSearchParams(value) {
+        return Object.prototype.toString.call(value) === '[object URLSearchParams]';
+    }
+
+    const URLENCODED_CONTENT_TYPE = 'application/x-www-form-urlencoded;charset=UTF-8';
+
+    // Parse a request URL (a string or URL object), rejecting malformed URLs before they reach the host
+    function parseRequestURL(input) {
+        const url = String(input);
+        if (typeof globalThis.URL === 'function') {
+            const parsed = globalThis.URL.parse ? globalThis.URL.parse(url) : null;
+            if (!parsed) {
+                throw new TypeError(`Failed to parse URL from ${url}`);
+            }
+            return parsed.href;
+        }
+        // Without a URL implementation only check that the URL is absolute
+        if (!/^[a-zA-Z][a-zA-Z0-9+.-]*:/.test(url)) {
+            throw new TypeError(`Failed to parse URL from ${url}`);
+        }
+        return url;
+    }
+
+    // Header names must be HTTP tokens and values may not contain NUL, CR or LF
+    const HEADER_NAME_RE = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/;
+    const HEADER_VALUE_RE = /^[^\0\r\n]*$/;
+
+    function normalizeHeaderName(name) {
+        name = String(name);
+        if (!HEADER_NAME_RE.test(name)) {
+            throw new TypeError(`Invalid header name: '${name}'`);
+        }
+        return name.toLowerCase();
+    }
+
+    function normalizeHeaderValue(value) {
+        // Strip leading and trailing HTTP whitespace
+        value = String(value).replace(/^[\t\n\r ]+|[\t\n\r ]+$/g, '');
+        if (!HEADER_VALUE_RE.test(value)) {
+            throw new TypeError(`Invalid header value: '${value}'`);
+        }
+        return value;
+    }
 
     class Headers {
         constructor(init) {
-            this._headers = {};
+            // Ordered list of [lowercase name, value] pairs, names may repeat
+            this._list = [];
             if (init) {
                 if (init instanceof Headers) {
-                    // Copy from another Headers instance
-                    for (const [key, value] of init.entries()) {
-                        this.append(key, value);
-                    }
-                } else if (Array.isArray(init)) {
-                    // Array of [key, value] pairs
+                    // Copy from another Headers instance, keeping repeated headers
+                    this._list = init._list.map(([name, value]) => [name, value]);
+                } else if (typeof init === 'object' && typeof init[Symbol.iterator] === 'function') {
+                    // Sequence of [name, value] pairs
                     for (const pair of init) {
-                        if (pair.length !== 2) {
+                        const entry = Array.from(pair);
+                        if (entry.length !== 2) {
                             throw new TypeError('Invalid headers array');
                         }
-                        this.append(pair[0], pair[1]);
+                        this.append(entry[0], entry[1]);
                     }
                 } else if (typeof init === 'object') {
                     // Object literal
@@ -30,50 +438,64 @@
         }
 
         append(name, value) {
-            name = this._normalizeHeaderName(name);
-            value = String(value);
-            
-            if (this._headers[name]) {
-                this._headers[name] += ', ' + value;
-            } else {
-                this._headers[name] = value;
-            }
+            this._list.push([normalizeHeaderName(name), normalizeHeaderValue(value)]);
         }
 
         delete(name) {
-            name = this._normalizeHeaderName(name);
-            delete this._headers[name];
+            name = normalizeHeaderName(name);
+            this._list = this._list.filter(entry => entry[0] !== name);
         }
 
         get(name) {
-            name = this._normalizeHeaderName(name);
-            return this._headers[name] || null;
+            name = normalizeHeaderName(name);
+            const values = this._list.filter(entry => entry[0] === name).map(entry => entry[1]);
+            return values.length > 0 ? values.join(', ') : null;
+        }
+
+        getSetCookie() {
+            return this._list.filter(entry => entry[0] === 'set-cookie').map(entry => entry[1]);
         }
 
         has(name) {
-            name = this._normalizeHeaderName(name);
-            return name in this._headers;
+            name = normalizeHeaderName(name);
+            return this._list.some(entry => entry[0] === name);
         }
 
         set(name, value) {
-            name = this._normalizeHeaderName(name);
-            this._headers[name] = String(value);
+            name = normalizeHeaderName(name);
+            const entry = [name, normalizeHeaderValue(value)];
+            const index = this._list.findIndex(entry => entry[0] === name);
+            if (index === -1) {
+                this._list.push(entry);
+            } else {
+                // Replace the first entry in place and drop the rest
+                this._list = this._list.filter((entry, i) => i <= index || entry[0] !== name);
+                this._list[index] = entry;
+            }
         }
 
+        // Names in sorted order with repeated headers combined, except Set-Cookie which stays separate
         *entries() {
-            for (const key of Object.keys(this._headers)) {
-                yield [key, this._headers[key]];
+            const names = [...new Set(this._list.map(entry => entry[0]))].sort();
+            for (const name of names) {
+                if (name === 'set-cookie') {
+                    for (const value of this.getSetCookie()) {
+                        yield [name, value];
+                    }
+                } else {
+                    yield [name, this.get(name)];
+                }
             }
         }
 
         *keys() {
-            for (const key of Object.keys(this._headers)) {
-                yield key;
+            for (const [name] of this.entries()) {
+                yield name;
             }
         }
 
         *values() {
-            for (const value of Object.values(this._headers)) {
+            for (const [, value] of this.entries()) {
                 yield value;
             }
         }
@@ -84,13 +506,15 @@
             }
         }
 
-        _normalizeHeaderName(name) {
-            return String(name).toLowerCase();
+        [Symbol.iterator]() {
+            return this.entries();
         }
 
-        // Convert to plain object for passing to Rust
-        _toObject() {
-            return { ...this._headers };
+        get [Symbol.toStringTag]() { return 'Headers'; }
+
+        // Ordered [name, value] pairs for passing to Rust
+        _toList() {
+            return this._list.map(([name, value]) => [name, value]);
         }
     }
 
@@ -111,9 +535,10 @@
                 this._integrity = init.integrity || input.integrity;
                 this._keepalive = init.keepalive !== undefined ? init.keepalive : input.keepalive;
                 this._signal = init.signal || input.signal;
+                this._duplex = init.duplex || input._duplex;
             } else {
-                // Create from URL
-                this._url = String(input);
+                // Create from a URL string or URL object
+                this._url = parseRequestURL(input);
                 this._method = init.method || 'GET';
                 this._headers = new Headers(init.headers);
                 this._body = init.body;
@@ -126,12 +551,21 @@
                 this._integrity = init.integrity || '';
                 this._keepalive = init.keepalive || false;
                 this._signal = init.signal;
+                this._duplex = init.duplex;
             }
 
             // Validate
             if (this._body && (this._method === 'GET' || this._method === 'HEAD')) {
                 throw new TypeError('Request with GET/HEAD method cannot have body');
             }
+
+            if (isURLSearchParams(this._body) && !this._headers.has('content-type')) {
+                this._headers.set('content-type', URLENCODED_CONTENT_TYPE);
+            }
+
+            if (this._body && typeof this._body === 'object' && isStreamBody(this._body) && this._duplex !== 'half') {
+                throw new TypeError("Failed to construct 'Request': The duplex member must be specified for a request with a streaming body");
+            }
         }
 
         get url() { return this._url; }
@@ -146,14 +580,20 @@
         get integrity() { return this._integrity; }
         get keepalive() { return this._keepalive; }
         get signal() { return this._signal; }
+        get duplex() { return 'half'; }
         clone() {
             return new Request(this);
         }
     }
 
+    // Statuses whose responses never have a body
+    const NULL_BODY_STATUSES = [101, 103, 204, 205, 304];
+
     class Response {
         constructor(body, init = {}) {
             this._body = body;
+            // The body as a ReadableStream, created on demand for bodies supplied from JavaScript
+            this._stream = body instanceof ReadableStream ? body : null;
             this._status = init.status || 200;
             this._statusText = init.statusText || '';
             this._headers = new Headers(init.headers);
@@ -162,10 +602,18 @@
             this._type = init.type || 'basic';
             this._ok = this._status >= 200 && this._status < 300;
             this._bodyUsed = false;
+            this._signal = null;
+
+            if (isURLSearchParams(body)) {
+                this._body = body.toString();
+                if (!this._headers.has('content-type')) {
+                    this._headers.set('content-type', URLENCODED_CONTENT_TYPE);
+                }
+            }
         }
 
         static _fromNative(nativeResponse) {
-            const response = new Response(nativeResponse.body || null);
+            const response = new Response(null);
 
             // Copy properties from native response
             response._status = nativeResponse.status;
@@ -175,16 +623,30 @@
             response._redirected = nativeResponse.redirected;
             response._type = nativeResponse.type;
 
-            // Convert headers object to Headers instance
-            response._headers = new Headers(nativeResponse.headers);
+            // Headers arrive as lowercase [name, value] pairs, repeated headers are separate entries
+            response._headers = new Headers();
+            response._headers._list = nativeResponse.headers.map(([name, value]) => [name, value]);
 
-            // Bind native methods
-            response._nativeText = nativeResponse.text;
-            response._nativeJson = nativeResponse.json;
-            response._nativeArrayBuffer = nativeResponse.arrayBuffer;
-            response._nativeBlob = nativeResponse.blob;
-            response._nativeClone = nativeResponse.clone;
+            if (NULL_BODY_STATUSES.includes(response._status) || response._type === 'opaqueredirect') {
+                nativeResponse.cancel();
+                return response;
+            }
 
+            // The body is pulled from the host one chunk at a time as the stream is read
+            response._stream = new ReadableStream({
+                pull(controller) {
+                    response._throwIfAborted();
+                    const chunk = nativeResponse.readChunk();
+                    if (chunk === null) {
+                        controller.close();
+                    } else {
+                        controller.enqueue(chunk);
+                    }
+                },
+                cancel() {
+                    nativeResponse.cancel();
+                },
+            });
             return response;
         }
 
@@ -195,79 +657,167 @@
         get url() { return this._url; }
         get redirected() { return this._redirected; }
         get type() { return this._type; }
-        get bodyUsed() { return this._bodyUsed; }
 
-        async text() {
-            if (this._bodyUsed) {
-                throw new TypeError('Body already consumed');
+        get body() {
+            if (this._stream === null && this._body !== null && this._body !== undefined) {
+                this._stream = this._body instanceof Blob
+                    ? this._body.stream()
+                    : Response._streamOf(this._bodyBytes());
             }
-            this._bodyUsed = true;
-            
-            if (this._nativeText) {
-                return this._nativeText();
+            return this._stream;
+        }
+
+        get bodyUsed() {
+            return this._bodyUsed || (this._stream !== null && this._stream._disturbed);
+        }
+
+        // A stream with a single chunk
+        static _streamOf(bytes) {
+            let done = false;
+            return new ReadableStream({
+                pull(controller) {
+                    if (done || bytes.byteLength === 0) {
+                        controller.close();
+                    } else {
+                        done = true;
+                        controller.enqueue(bytes);
+                    }
+                },
+            });
+        }
+
+        // Reading the body rejects once the request's signal has been aborted
+        _throwIfAborted() {
+            if (this._signal && this._signal.aborted) {
+                throw this._signal.reason;
             }
-            return String(this._body || '');
         }
 
-        async json() {
-            if (this._bodyUsed) {
+        // Mark the body as used, rejecting if it was already read or the request was aborted
+        _consumeBody() {
+            this._throwIfAborted();
+            if (this.bodyUsed) {
                 throw new TypeError('Body already consumed');
             }
+            if (this._stream !== null && this._stream.locked) {
+                throw new TypeError('Body is locked by a reader');
+            }
             this._bodyUsed = true;
-            if (this._nativeJson) {
-                return this._nativeJson();
+        }
+
+        // Bytes of a body supplied from JavaScript (string, ArrayBuffer or view)
+        _bodyBytes() {
+            const body = this._body;
+            if (body === null || body === undefined) {
+                return new Uint8Array(0);
+            }
+            if (body instanceof Blob) {
+                return __javy_bless_blob_read(body._handle);
+            }
+            if (body instanceof ArrayBuffer) {
+                return new Uint8Array(body.slice(0));
             }
-            const text = await this.text();
-            return JSON.parse(text);
+            if (ArrayBuffer.isView(body)) {
+                return new Uint8Array(body.buffer.slice(body.byteOffset, body.byteOffset + body.byteLength));
+            }
+            return new TextEncoder().encode(String(body));
         }
 
-        async arrayBuffer() {
-            if (this._bodyUsed) {
-                throw new TypeError('Body already consumed');
+        // Read the whole body, from the stream if there is one
+        async _readAllBytes() {
+            if (this._stream === null) {
+                return this._bodyBytes();
             }
-            this._bodyUsed = true;
-            
-            if (this._nativeArrayBuffer) {
-                return this._nativeArrayBuffer();
+            const chunks = [];
+            let length = 0;
+            const reader = this._stream.getReader();
+            try {
+                for (;;) {
+                    const { value, done } = await reader.read();
+                    if (done) {
+                        break;
+                    }
+                    const chunk = value instanceof Uint8Array ? value : new Uint8Array(value);
+                    chunks.push(chunk);
+                    length += chunk.byteLength;
+                }
+            } finally {
+                reader.releaseLock();
             }
-            
-            // Simplified implementation
-            const text = await this.text();
-            const encoder = new TextEncoder();
-            return encoder.encode(text).buffer;
+            if (chunks.length === 1) {
+                return chunks[0];
+            }
+            const bytes = new Uint8Array(length);
+            let offset = 0;
+            for (const chunk of chunks) {
+                bytes.set(chunk, offset);
+                offset += chunk.byteLength;
+            }
+            return bytes;
+        }
+
+        async text() {
+            this._consumeBody();
+            if (this._stream === null && typeof this._body === 'string') {
+                return this._body;
+            }
+            return new TextDecoder().decode(await this._readAllBytes());
+        }
+
+        async json() {
+            return JSON.parse(await this.text());
+        }
+
+        async arrayBuffer() {
+            this._consumeBody();
+            const bytes = await this._readAllBytes();
+            return bytes.byteOffset === 0 && bytes.byteLength === bytes.buffer.byteLength
+                ? bytes.buffer
+                : bytes.slice().buffer;
+        }
+
+        async bytes() {
+            this._consumeBody();
+            return this._readAllBytes();
         }
 
         async blob() {
-            if (this._bodyUsed) {
-                throw new TypeError('Body already consumed');
+            this._consumeBody();
+            const type = this.headers.get('content-type') || '';
+            if (this._stream === null && this._body instanceof Blob) {
+                return Blob._fromHandle(this._body._handle, type || this._body.type);
             }
-            this._bodyUsed = true;
+            return new Blob([await this._readAllBytes()], { type });
+        }
 
-            if (this._nativeBlob) {
-                return this._nativeBlob();
+        async formData() {
+            this._consumeBody();
+            const contentType = this.headers.get('content-type') || '';
+            const body = await this._readAllBytes();
+
+            let entries;
+            try {
+                entries = __javy_bless_form_data_parse(body, contentType);
+            } catch (error) {
+                throw new TypeError(error.message);
             }
 
-            // Return blob-like object
-            const arrayBuffer = await this.arrayBuffer();
-            return {
-                size: arrayBuffer.byteLength,
-                type: this.headers.get('content-type') || 'application/octet-stream',
-                arrayBuffer: () => Promise.resolve(arrayBuffer),
-                text: () => this.text(),
-            };
+            const formData = new FormData();
+            for (const [name, value, filename, type] of entries) {
+                if (typeof value === 'string') {
+                    formData.append(name, value);
+                } else {
+                    formData.append(name, Blob._fromHandle(value, type), filename);
+                }
+            }
+            return formData;
         }
 
         clone() {
-            if (this._bodyUsed) {
+            if (this.bodyUsed) {
                 throw new TypeError('Cannot clone a response with used body');
             }
 
-            // If this response came from native code (has native methods), call native clone
-            if (this._nativeClone) {
-                return Response._fromNative(this._nativeClone());
-            }
-
-            // Otherwise create a JavaScript clone
             const clonedResponse = new Response(this._body, {
                 status: this._status,
                 statusText: this._statusText,
@@ -276,12 +826,12 @@
                 redirected: this._redirected,
                 type: this._type,
             });
-            
-            // Copy native methods if they exist
-            if (this._nativeText) clonedResponse._nativeText = this._nativeText;
-            if (this._nativeJson) clonedResponse._nativeJson = this._nativeJson;
-            if (this._nativeArrayBuffer) clonedResponse._nativeArrayBuffer = this._nativeArrayBuffer;
-            if (this._nativeBlob) clonedResponse._nativeBlob = this._nativeBlob;
+            clonedResponse._signal = this._signal;
+
+            // Streamed bodies are split so each response reads every chunk
+            if (this._stream !== null) {
+                [this._stream, clonedResponse._stream] = this._stream.tee();
+            }
 
             return clonedResponse;
         }
@@ -296,12 +846,32 @@
             if (![301, 302, 303, 307, 308].includes(status)) {
                 throw new RangeError('Invalid redirect status');
             }
-            const headers = new Headers({ Location: url });
+            const headers = new Headers({ Location: parseRequestURL(url) });
             return new Response(null, { status, headers });
         }
     }
 
     // FormData class implementation
+    // Convert a FormData value to an entry value: strings stay strings, blobs and binary data become Files
+    function toFormDataEntryValue(value, filename) {
+        if (value instanceof ArrayBuffer || ArrayBuffer.isView(value)) {
+            value = new Blob([value]);
+        }
+        if (!(value instanceof Blob)) {
+            return String(value);
+        }
+        if (value instanceof File && filename === undefined) {
+            return value;
+        }
+        // Wrap the blob's bytes in a File without copying them
+        const file = Object.create(File.prototype);
+        file._handle = value._handle;
+        file._type = value.type;
+        file._name = filename !== undefined ? String(filename) : (value instanceof File ? value.name : 'blob');
+        file._lastModified = value instanceof File ? value.lastModified : Date.now();
+        return file;
+    }
+
     class FormData {
         constructor() {
             this._entries = [];
@@ -309,7 +879,7 @@
         }
 
         append(name, value, filename) {
-            this._entries.push([String(name), String(value), filename]);
+            this._entries.push([String(name), toFormDataEntryValue(value, filename)]);
         }
 
         delete(name) {
@@ -337,8 +907,15 @@
 
         set(name, value, filename) {
             name = String(name);
-            this.delete(name);
-            this.append(name, value, filename);
+            const entry = [name, toFormDataEntryValue(value, filename)];
+            const index = this._entries.findIndex(entry => entry[0] === name);
+            if (index === -1) {
+                this._entries.push(entry);
+            } else {
+                // Replace the first entry in place and drop the rest
+                this._entries = this._entries.filter((entry, i) => i <= index || entry[0] !== name);
+                this._entries[index] = entry;
+            }
         }
 
         *entries() {
@@ -367,6 +944,45 @@
     }
 
     // Main fetch function
+    //
+    // `fetch` returns before the request is sent, which happens once the script yields to the
+    // event loop. The host has no way to send several requests at once: each host call blocks
+    // the whole runtime until its response arrives. Requests started together are therefore
+    // sent one after another, and `Promise.all` over N fetches takes as long as the N requests
+    // in a row. What starting them together buys is only that they are validated up front and
+    // that requests aborted before their turn are never sent.
+
+    // Requests that were started and wait to be sent from the event loop
+    const pendingRequests = [];
+
+    // Send every started request, one after another in the order they were started
+    function completePendingRequests() {
+        while (pendingRequests.length > 0) {
+            const { handle, signal, resolve, reject } = pendingRequests.shift();
+            // Requests aborted while they were waiting never reach the host
+            if (signal && signal.aborted) {
+                reject(signal.reason);
+                continue;
+            }
+            try {
+                resolve(__javy_fetchio_complete(handle));
+            } catch (error) {
+                reject(error);
+            }
+        }
+    }
+
+    // Start a request, returning a promise for the native response
+    function startRequest(url, options, signal) {
+        const handle = __javy_fetchio_start(url, options);
+        return new Promise((resolve, reject) => {
+            pendingRequests.push({ handle, signal, resolve, reject });
+            if (pendingRequests.length === 1) {
+                Promise.resolve().then(completePendingRequests);
+            }
+        });
+    }
+
     async function fetch(input, init = {}) {
         let url;
         let options = {};
@@ -375,7 +991,7 @@
             url = input.url;
             options = {
                 method: input.method,
-                headers: input.headers._toObject(),
+                headers: input.headers,
                 body: input._body,
                 mode: input.mode,
                 credentials: input.credentials,
@@ -386,68 +1002,364 @@
                 integrity: input.integrity,
                 keepalive: input.keepalive,
                 signal: input.signal,
+                duplex: input._duplex,
                 ...init, // init overrides
             };
         } else {
-            url = String(input);
+            url = parseRequestURL(input);
             options = {
                 method: 'GET',
                 ...init,
             };
         }
 
-        // Convert headers to object if Headers instance
-        if (options.headers instanceof Headers) {
-            options.headers = options.headers._toObject();
-        }
+        // Normalize headers into a Headers instance so repeated headers are kept
+        const headers = new Headers(options.headers);
 
         // Handle body
         if (options.body) {
             if (typeof options.body === 'string') {
                 // String body - pass as is
             } else if (options.body instanceof FormData) {
-                // FormData - convert to format expected by Rust
+                // FormData - convert to [name, value, filename, type] entries, files are passed as blob handles
                 options.body = {
                     _isFormData: true,
-                    _entries: options.body._entries.map(([name, value]) => [name, value]),
+                    _entries: options.body._entries.map(([name, value]) => value instanceof File
+                        ? [name, value._handle, value.name, value.type]
+                        : [name, value]),
                 };
+            } else if (isURLSearchParams(options.body)) {
+                // URLSearchParams - sent urlencoded
+                if (!headers.has('content-type')) {
+                    headers.set('content-type', URLENCODED_CONTENT_TYPE);
+                }
+                options.body = options.body.toString();
+            } else if (options.body instanceof Blob) {
+                // Blob - the host binding reads the bytes straight from the blob handle
+                if (options.body.type && !headers.has('content-type')) {
+                    headers.set('content-type', options.body.type);
+                }
+                options.body = options.body._handle;
             } else if (ArrayBuffer.isView(options.body) || options.body instanceof ArrayBuffer) {
-                // Binary data - convert to array
-                const view = new Uint8Array(options.body);
-                options.body = Array.from(view);
+                // Binary data - passed as is, the host binding reads it from the backing store
+            } else if (typeof options.body === 'object' && isDescriptor(options.body)) {
+                // Open file - read from the file descriptor in Rust, whole and up to 32 MiB, as the
+                // host takes the request body in a single call
+                options.body = __javy_bless_upload_from_fd(options.body.rawfd);
+            } else if (typeof options.body === 'object' && isStreamBody(options.body)) {
+                // ReadableStream or async iterable - collected chunk by chunk outside of QuickJS memory,
+                // up to 32 MiB, and sent once the stream ends: the request is not sent in chunks
+                if (options.duplex !== 'half') {
+                    throw new TypeError("Failed to execute 'fetch': The duplex member must be specified for a request with a streaming body");
+                }
+                options.body = await collectStreamBody(options.body, options.signal);
             } else if (typeof options.body === 'object') {
                 // JSON - stringify
                 options.body = JSON.stringify(options.body);
-                if (!options.headers) {
-                    options.headers = {};
-                }
-                if (!options.headers['content-type'] && !options.headers['Content-Type']) {
-                    options.headers['Content-Type'] = 'application/json';
+                if (!headers.has('content-type')) {
+                    headers.set('content-type', 'application/json');
                 }
             }
         }
 
+        // Headers are passed to Rust as an ordered list of [name, value] pairs
+        options.headers = headers._toList();
+
+        // A signal that is already aborted stops the request before it reaches the host
+        const signal = options.signal;
+        if (signal && signal.aborted) {
+            throw signal.reason;
+        }
+
+        let response;
         try {
-            // Call native fetch function
-            const nativeResponse = __javy_fetchio_request(url, options);
+            // The request is validated now and sent from the event loop
+            const nativeResponse = await startRequest(url, options, signal);
             // Convert native response to Response instance
-            return Response._fromNative(nativeResponse);
+            response = Response._fromNative(nativeResponse);
         } catch (error) {
-            // Network errors should return a network error response
-            if (error.message && error.message.includes('network')) {
-                return Response.error();
+            // A timeout signal may have expired while the host was waiting
+            if (signal && signal.aborted) {
+                throw signal.reason;
             }
+            // Failures arrive as a TypeError with a `code` and `cause`, or a TimeoutError/AbortError DOMException
             throw error;
         }
+
+        if (signal) {
+            signal.throwIfAborted();
+            response._signal = signal;
+        }
+        return response;
+    }
+
+    const EVENT_STREAM_CONTENT_TYPE = 'text/event-stream';
+
+    function isEventStream(response) {
+        const contentType = response.headers.get('content-type') || '';
+        return contentType.split(';')[0].trim().toLowerCase() === EVENT_STREAM_CONTENT_TYPE;
+    }
+
+    function originOf(url) {
+        const match = /^[a-z][a-z0-9+.-]*:\/\/[^/?#]*/i.exec(url);
+        return match ? match[0].toLowerCase() : '';
+    }
+
+    // Javy has no timers, so reconnecting only waits when the host provides setTimeout
+    function delay(ms) {
+        return typeof globalThis.setTimeout === 'function'
+            ? new Promise(resolve => globalThis.setTimeout(resolve, ms))
+            : Promise.resolve();
+    }
+
+    // Parse the chunks of a text/event-stream body, yielding { event, data, id, retry } objects
+    async function* readEventStream(body, parser) {
+        if (body === null) {
+            return;
+        }
+        for await (const chunk of body) {
+            yield* __javy_bless_event_stream_parse(parser, chunk);
+        }
+    }
+
+    // Fetch a text/event-stream resource and iterate over its events.
+    // The host hands over the response only once the server has finished sending it, so no
+    // event is delivered before the stream ends. Long-lived streams deliver nothing at all.
+    async function* fetchEventStream(input, init = {}) {
+        const { lastEventId, ...options } = init;
+        const headers = new Headers(options.headers);
+        if (!headers.has('accept')) {
+            headers.set('accept', EVENT_STREAM_CONTENT_TYPE);
+        }
+        const resumeFrom = lastEventId ? String(lastEventId) : '';
+        if (resumeFrom) {
+            headers.set('last-event-id', resumeFrom);
+        }
+
+        // Event streams cannot be read with range requests
+        const response = await fetch(input, { cache: 'no-store', ...options, headers, chunkSize: 0 });
+        if (!response.ok) {
+            throw new TypeError(`fetchEventStream: unexpected response status ${response.status}`);
+        }
+        if (!isEventStream(response)) {
+            throw new TypeError(`fetchEventStream: expected a ${EVENT_STREAM_CONTENT_TYPE} response, got '${response.headers.get('content-type') || ''}'`);
+        }
+        yield* readEventStream(response.body, __javy_bless_event_stream_create(resumeFrom));
+    }
+
+    class MessageEvent extends Event {
+        constructor(type, init = {}) {
+            super(type, init);
+            this._data = init.data === undefined ? null : init.data;
+            this._origin = init.origin || '';
+            this._lastEventId = init.lastEventId || '';
+        }
+
+        get data() { return this._data; }
+        get origin() { return this._origin; }
+        get lastEventId() { return this._lastEventId; }
     }
 
+    // Default reconnection delay, streams can change it with a `retry` field
+    const EVENT_SOURCE_RETRY_MS = 3000;
+    // Reconnections before the connection is given up
+    const EVENT_SOURCE_MAX_RETRIES = 3;
+
+    // Like fetchEventStream, events are dispatched only once each response has completed.
+    // Reconnections wait for the retry delay when the host provides setTimeout, and are
+    // immediate otherwise. Without timers every reconnection counts against `maxRetries`,
+    // with them only reconnections in a row that receive no events do.
+    class EventSource extends EventTarget {
+        constructor(url, init = {}) {
+            super();
+            try {
+                this._url = parseRequestURL(url);
+            } catch {
+                throw new DOMException(`Failed to construct 'EventSource': The URL '${url}' is invalid.`, 'SyntaxError');
+            }
+            this._withCredentials = !!init.withCredentials;
+            this._readyState = EventSource.CONNECTING;
+            this._lastEventId = '';
+            this._retry = EVENT_SOURCE_RETRY_MS;
+            // Non-standard, reconnecting is bounded since there may be no timers to back off with
+            this._maxRetries = init.maxRetries === undefined ? EVENT_SOURCE_MAX_RETRIES : Number(init.maxRetries);
+            this._controller = new AbortController();
+            this.onopen = null;
+            this.onmessage = null;
+            this.onerror = null;
+
+            // Connect on the event loop, once listeners had a chance to be added
+            Promise.resolve().then(() => this._run());
+        }
+
+        static get CONNECTING() { return 0; }
+        static get OPEN() { return 1; }
+        static get CLOSED() { return 2; }
+        get CONNECTING() { return 0; }
+        get OPEN() { return 1; }
+        get CLOSED() { return 2; }
+
+        get url() { return this._url; }
+        get withCredentials() { return this._withCredentials; }
+        get readyState() { return this._readyState; }
+
+        close() {
+            if (this._readyState !== EventSource.CLOSED) {
+                this._readyState = EventSource.CLOSED;
+                this._controller.abort();
+            }
+        }
+
+        async _run() {
+            let failures = 0;
+            while (this._readyState !== EventSource.CLOSED) {
+                // The last event ID carries over from the previous connection
+                const parser = __javy_bless_event_stream_create(this._lastEventId);
+                let received = false;
+                try {
+                    const headers = new Headers({ accept: EVENT_STREAM_CONTENT_TYPE });
+                    if (this._lastEventId) {
+                        headers.set('last-event-id', this._lastEventId);
+                    }
+                    const response = await fetch(this._url, {
+                        headers,
+                        cache: 'no-store',
+                        credentials: this._withCredentials ? 'include' : 'same-origin',
+                        signal: this._controller.signal,
+                        chunkSize: 0,
+                    });
+                    if (this._readyState === EventSource.CLOSED) {
+                        return;
+                    }
+                    // Anything but a 200 event stream fails the connection for good
+                    if (response.status !== 200 || !isEventStream(response)) {
+                        this._fail();
+                        return;
+                    }
+
+                    this._readyState = EventSource.OPEN;
+                    this.dispatchEvent(new Event('open'));
+                    const origin = originOf(response.url || this._url);
+                    for await (const event of readEventStream(response.body, parser)) {
+                        if (this._readyState === EventSource.CLOSED) {
+                            return;
+                        }
+                        received = true;
+                        this._lastEventId = event.id;
+                        this.dispatchEvent(new MessageEvent(event.event, {
+                            data: event.data,
+                            origin,
+                            lastEventId: event.id,
+                        }));
+                    }
+                } catch {
+                    // Network errors reconnect like a closed stream
+                }
+                if (this._readyState === EventSource.CLOSED) {
+                    return;
+                }
+
+                const retry = __javy_bless_event_stream_retry(parser);
+                if (retry !== null) {
+                    this._retry = retry;
+                }
+                failures = received && typeof globalThis.setTimeout === 'function' ? 0 : failures + 1;
+                if (failures > this._maxRetries) {
+                    this._fail();
+                    return;
+                }
+
+                // Reestablish the connection, resuming from the last event ID
+                this._readyState = EventSource.CONNECTING;
+                this.dispatchEvent(new Event('error'));
+                await delay(this._retry);
+            }
+        }
+
+        _fail() {
+            this._readyState = EventSource.CLOSED;
+            this.dispatchEvent(new Event('error'));
+        }
+    }
+
+    // The HTTP cache used by fetch (non-standard)
+    const fetchCache = Object.freeze({
+        // Also store responses in a directory, which must be inside a preopened WASI directory
+        persist(directory) {
+            __javy_bless_fetch_cache_persist(String(directory));
+        },
+
+        // Remove every stored response
+        clear() {
+            __javy_bless_fetch_cache_clear();
+        },
+    });
+
+    // The cookie jar used by fetch (non-standard). It is off until enabled, then cookies are
+    // stored from responses and sent with requests whose `credentials` mode allows it.
+    const fetchCookies = Object.freeze({
+        enable() {
+            __javy_bless_cookie_jar_enable(true);
+        },
+
+        disable() {
+            __javy_bless_cookie_jar_enable(false);
+        },
+
+        // Stored cookies, or only those that would be sent to `url`
+        getAll(url) {
+            return __javy_bless_cookie_jar_list(url === undefined ? undefined : String(url));
+        },
+
+        // The value of the cookie named `name` that would be sent to `url`, or null
+        get(name, url) {
+            const cookie = __javy_bless_cookie_jar_list(String(url)).find((cookie) => cookie.name === name);
+            return cookie ? cookie.value : null;
+        },
+
+        // Remove every cookie, or those of one domain and its subdomains
+        clear(domain) {
+            __javy_bless_cookie_jar_clear(domain === undefined ? undefined : String(domain));
+        },
+    });
+
     // Expose global APIs
     globalThis.fetch = fetch;
     globalThis.Headers = Headers;
     globalThis.Request = Request;
     globalThis.Response = Response;
     globalThis.FormData = FormData;
+    globalThis.Blob = Blob;
+    globalThis.File = File;
+    globalThis.AbortController = AbortController;
+    globalThis.AbortSignal = AbortSignal;
+    globalThis.Event = globalThis.Event || Event;
+    globalThis.EventTarget = globalThis.EventTarget || EventTarget;
+    globalThis.DOMException = DOMException;
+    globalThis.MessageEvent = globalThis.MessageEvent || MessageEvent;
+    globalThis.EventSource = EventSource;
+    globalThis.fetchEventStream = fetchEventStream;
+    globalThis.fetchCache = fetchCache;
+    globalThis.fetchCookies = fetchCookies;
 
-    // Delete the native function from `globalThis` so it doesn't leak.
-    Reflect.deleteProperty(globalThis, "__javy_fetchio_request");
+    // Delete the native functions from `globalThis` so they don't leak.
+    Reflect.deleteProperty(globalThis, "__javy_fetchio_start");
+    Reflect.deleteProperty(globalThis, "__javy_fetchio_complete");
+    Reflect.deleteProperty(globalThis, "__javy_bless_blob_create");
+    Reflect.deleteProperty(globalThis, "__javy_bless_blob_slice");
+    Reflect.deleteProperty(globalThis, "__javy_bless_blob_size");
+    Reflect.deleteProperty(globalThis, "__javy_bless_blob_read");
+    Reflect.deleteProperty(globalThis, "__javy_bless_blob_text");
+    Reflect.deleteProperty(globalThis, "__javy_bless_form_data_parse");
+    Reflect.deleteProperty(globalThis, "__javy_bless_upload_create");
+    Reflect.deleteProperty(globalThis, "__javy_bless_upload_write");
+    Reflect.deleteProperty(globalThis, "__javy_bless_upload_from_fd");
+    Reflect.deleteProperty(globalThis, "__javy_bless_event_stream_create");
+    Reflect.deleteProperty(globalThis, "__javy_bless_event_stream_parse");
+    Reflect.deleteProperty(globalThis, "__javy_bless_event_stream_retry");
+    Reflect.deleteProperty(globalThis, "__javy_bless_fetch_cache_persist");
+    Reflect.deleteProperty(globalThis, "__javy_bless_fetch_cache_clear");
+    Reflect.deleteProperty(globalThis, "__javy_bless_cookie_jar_enable");
+    Reflect.deleteProperty(globalThis, "__javy_bless_cookie_jar_list");
+    Reflect.deleteProperty(globalThis, "__javy_bless_cookie_jar_clear");
 })();
@@ -0,0 +1,442 @@
+use anyhow::{anyhow, bail, Result};
+use javy_plugin_api::javy::{
+    quickjs::{Array, Ctx, String as JSString, Value},
+    Args,
+};
+use std::rc::Rc;
+
+use super::{blob::BlobData, read_binary_data};
+
+/// A decoded form entry, files keep a view onto the body bytes
+enum FormValue {
+    Text(String),
+    File {
+        data: BlobData,
+        filename: String,
+        content_type: String,
+    },
+}
+
+/// Decode a form body into FormData entries.
+/// Takes the body (a blob handle or binary data) and the response Content-Type, and returns
+/// an array of `[name, value]` entries for text fields and `[name, blob, filename, type]` for files.
+pub fn bless_form_data_parse(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let [body, content_type, ..] = args.as_slice() else {
+        bail!(
+            "form_data_parse expects 2 parameters: the body and content type, Got: {} parameters.",
+            args.len()
+        );
+    };
+
+    let body = match body.as_object() {
+        Some(obj) => match BlobData::from_object(obj) {
+            Some(blob) => blob,
+            None => BlobData::new(Rc::from(
+                read_binary_data(obj)?.ok_or_else(|| anyhow!("form body must be binary data"))?,
+            )),
+        },
+        None => bail!("form body must be binary data"),
+    };
+    let content_type = content_type
+        .as_string()
+        .map(|s| s.to_string())
+        .transpose()
+        .map_err(|_| anyhow!("invalid UTF-8 in content type"))?
+        .unwrap_or_default();
+
+    entries_to_js(cx, parse_form(&body, &content_type)?)
+}
+
+/// Decode a form body as its Content-Type says
+fn parse_form(body: &BlobData, content_type: &str) -> Result<Vec<(String, FormValue)>> {
+    let (essence, params) = parse_content_type(content_type);
+    match essence.as_str() {
+        "multipart/form-data" => {
+            let boundary = params
+                .iter()
+                .find(|(key, _)| key == "boundary")
+                .map(|(_, value)| value.as_str())
+                .filter(|boundary| !boundary.is_empty())
+                .ok_or_else(|| anyhow!("multipart/form-data body has no boundary"))?;
+            parse_multipart(body, boundary)
+        }
+        "application/x-www-form-urlencoded" => Ok(parse_urlencoded(body.bytes())
+            .into_iter()
+            .map(|(name, value)| (name, FormValue::Text(value)))
+            .collect()),
+        _ => bail!(
+            "Could not parse content as FormData: unsupported content type '{}'",
+            content_type
+        ),
+    }
+}
+
+fn entries_to_js(cx: Ctx<'_>, entries: Vec<(String, FormValue)>) -> Result<Value<'_>> {
+    let array = Array::new(cx.clone())?;
+    for (i, (name, value)) in entries.into_iter().enumerate() {
+        let entry = Array::new(cx.clone())?;
+        entry.set(0, JSString::from_str(cx.clone(), &name)?)?;
+        match value {
+            FormValue::Text(text) => {
+                entry.set(1, JSString::from_str(cx.clone(), &text)?)?;
+            }
+            FormValue::File {
+                data,
+                filename,
+                content_type,
+            } => {
+                entry.set(1, data.into_js(cx.clone())?)?;
+                entry.set(2, JSString::from_str(cx.clone(), &filename)?)?;
+                entry.set(3, JSString::from_str(cx.clone(), &content_type)?)?;
+            }
+        }
+        array.set(i, entry)?;
+    }
+    Ok(array.into_value())
+}
+
+/// Split a header value such as a Content-Type into its lowercased essence and parameters
+fn parse_content_type(value: &str) -> (String, Vec<(String, String)>) {
+    let (essence, params) = value.split_once(';').unwrap_or((value, ""));
+    (essence.trim().to_ascii_lowercase(), parse_params(params))
+}
+
+/// Parse `; key=value; key="quoted value"` parameters, keys are lowercased
+fn parse_params(input: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        // Skip separators and whitespace before the key
+        while chars
+            .peek()
+            .is_some_and(|c| *c == ';' || c.is_ascii_whitespace())
+        {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=' && *c != ';') {
+            key.push(c);
+        }
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+                // Ignore anything between the closing quote and the next parameter
+                while chars.next_if(|c| *c != ';').is_some() {}
+            } else {
+                while let Some(c) = chars.next_if(|c| *c != ';') {
+                    value.push(c);
+                }
+                value.truncate(value.trim_end().len());
+            }
+        }
+
+        let key = key.trim().to_ascii_lowercase();
+        if !key.is_empty() {
+            params.push((key, value));
+        }
+    }
+
+    params
+}
+
+/// Parse an `application/x-www-form-urlencoded` body into name/value pairs
+fn parse_urlencoded(input: &[u8]) -> Vec<(String, String)> {
+    input
+        .split(|b| *b == b'&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = match pair.iter().position(|b| *b == b'=') {
+                Some(i) => (&pair[..i], &pair[i + 1..]),
+                None => (pair, &[][..]),
+            };
+            (percent_decode_plus(name), percent_decode_plus(value))
+        })
+        .collect()
+}
+
+/// Percent-decode a urlencoded component, treating `+` as a space.
+/// Invalid escapes are kept as-is and invalid UTF-8 is replaced.
+fn percent_decode_plus(input: &[u8]) -> String {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < input.len() => match (hex(input[i + 1]), hex(input[i + 2])) {
+                (Some(hi), Some(lo)) => {
+                    out.push(hi << 4 | lo);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Find the first occurrence of `needle` in `haystack` at or after `from`
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i + from)
+}
+
+/// Parse a `multipart/form-data` body. File parts share the bytes of the body.
+fn parse_multipart(body: &BlobData, boundary: &str) -> Result<Vec<(String, FormValue)>> {
+    let bytes = body.bytes();
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let malformed = || anyhow!("Could not parse content as FormData: malformed multipart body");
+
+    let mut entries = Vec::new();
+    let mut pos = find(bytes, &delimiter, 0).ok_or_else(malformed)? + delimiter.len();
+
+    loop {
+        // The closing delimiter is followed by `--`
+        if bytes[pos..].starts_with(b"--") {
+            break;
+        }
+        // Skip transport padding and the line break after the delimiter
+        while bytes.get(pos).is_some_and(|b| *b == b' ' || *b == b'\t') {
+            pos += 1;
+        }
+        if !bytes[pos..].starts_with(b"\r\n") {
+            return Err(malformed());
+        }
+        pos += 2;
+
+        let headers_end = find(bytes, b"\r\n\r\n", pos).ok_or_else(malformed)?;
+        let headers = String::from_utf8_lossy(&bytes[pos..headers_end]);
+        let content_start = headers_end + 4;
+
+        let mut next_delimiter = b"\r\n".to_vec();
+        next_delimiter.extend_from_slice(&delimiter);
+        let content_end = find(bytes, &next_delimiter, content_start).ok_or_else(malformed)?;
+
+        let mut name = None;
+        let mut filename = None;
+        let mut content_type = None;
+        for line in headers.split("\r\n") {
+            let Some((header, value)) = line.split_once(':') else {
+                continue;
+            };
+            match header.trim().to_ascii_lowercase().as_str() {
+                "content-disposition" => {
+                    let (disposition, params) = parse_content_type(value);
+                    if disposition != "form-data" {
+                        return Err(malformed());
+                    }
+                    for (key, value) in params {
+                        match key.as_str() {
+                            "name" => name = Some(value),
+                            "filename" => filename = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                "content-type" => content_type = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+
+        let name = name.ok_or_else(malformed)?;
+        let value = match filename {
+            Some(filename) => FormValue::File {
+                data: body.slice(content_start, content_end),
+                filename,
+                content_type: content_type.unwrap_or_else(|| "text/plain".to_string()),
+            },
+            None => FormValue::Text(
+                String::from_utf8_lossy(&bytes[content_start..content_end]).into_owned(),
+            ),
+        };
+        entries.push((name, value));
+
+        pos = content_end + next_delimiter.len();
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Entries as `(name, value, filename, content type)`, text fields have no filename
+    fn parse(body: &[u8], content_type: &str) -> Result<Vec<(String, String, String, String)>> {
+        let body = BlobData::new(Rc::from(body));
+        Ok(parse_form(&body, content_type)?
+            .into_iter()
+            .map(|(name, value)| match value {
+                FormValue::Text(text) => (name, text, String::new(), String::new()),
+                FormValue::File {
+                    data,
+                    filename,
+                    content_type,
+                } => (
+                    name,
+                    String::from_utf8_lossy(data.bytes()).into_owned(),
+                    filename,
+                    content_type,
+                ),
+            })
+            .collect())
+    }
+
+    fn entry(
+        name: &str,
+        value: &str,
+        filename: &str,
+        content_type: &str,
+    ) -> (String, String, String, String) {
+        (
+            name.to_string(),
+            value.to_string(),
+            filename.to_string(),
+            content_type.to_string(),
+        )
+    }
+
+    const MULTIPART: &[u8] = b"preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        two\r\nlines\r\n\
+        --XyZ  \r\n\
+        content-disposition: form-data; name=\"upload\"; filename=\"a \\\"b\\\".txt\"\r\n\
+        Content-Type: application/json\r\n\
+        \r\n\
+        {\"a\":1}\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=empty; filename=\"\"\r\n\
+        \r\n\
+        \r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    #[test]
+    fn multipart_bodies_are_parsed() {
+        assert_eq!(
+            parse(MULTIPART, "multipart/form-data; boundary=XyZ").unwrap(),
+            vec![
+                entry("title", "two\r\nlines", "", ""),
+                entry("upload", "{\"a\":1}", "a \"b\".txt", "application/json"),
+                entry("empty", "", "", "text/plain"),
+            ]
+        );
+    }
+
+    #[test]
+    fn multipart_boundaries_may_be_quoted() {
+        let entries = parse(
+            MULTIPART,
+            "Multipart/Form-Data; charset=utf-8; Boundary=\"XyZ\"",
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[test]
+    fn multipart_bodies_need_a_boundary() {
+        for content_type in [
+            "multipart/form-data",
+            "multipart/form-data; boundary=",
+            "multipart/form-data; boundary=\"\"",
+        ] {
+            assert_eq!(
+                parse(MULTIPART, content_type).unwrap_err().to_string(),
+                "multipart/form-data body has no boundary"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_multipart_bodies_fail() {
+        let content_type = "multipart/form-data; boundary=XyZ";
+        let bodies: [&[u8]; 6] = [
+            // Wrong boundary
+            b"--abc\r\nContent-Disposition: form-data; name=a\r\n\r\nx\r\n--abc--",
+            // Bare LF line breaks
+            b"--XyZ\nContent-Disposition: form-data; name=a\n\nx\n--XyZ--",
+            // No closing delimiter
+            b"--XyZ\r\nContent-Disposition: form-data; name=a\r\n\r\nx",
+            // No name
+            b"--XyZ\r\nContent-Disposition: form-data\r\n\r\nx\r\n--XyZ--",
+            // Not form-data
+            b"--XyZ\r\nContent-Disposition: attachment; name=a\r\n\r\nx\r\n--XyZ--",
+            // Ends right after a part
+            b"--XyZ\r\nContent-Disposition: form-data; name=a\r\n\r\nx\r\n--XyZ",
+        ];
+        for body in bodies {
+            let error = parse(body, content_type).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "Could not parse content as FormData: malformed multipart body"
+            );
+        }
+        let empty = b"--XyZ--\r\n";
+        assert!(parse(empty, content_type).unwrap().is_empty());
+    }
+
+    #[test]
+    fn urlencoded_bodies_are_parsed() {
+        assert_eq!(
+            parse(
+                b"a=1&b=two+words&&c&d=%E2%9C%93&e=&a=%3D%26",
+                "application/x-www-form-urlencoded;charset=UTF-8"
+            )
+            .unwrap(),
+            vec![
+                entry("a", "1", "", ""),
+                entry("b", "two words", "", ""),
+                entry("c", "", "", ""),
+                entry("d", "\u{2713}", "", ""),
+                entry("e", "", "", ""),
+                entry("a", "=&", "", ""),
+            ]
+        );
+        assert!(parse(b"a=1", "text/plain").is_err());
+    }
+
+    #[test]
+    fn invalid_percent_escapes_are_kept() {
+        assert_eq!(percent_decode_plus(b"100%"), "100%");
+        assert_eq!(percent_decode_plus(b"%4"), "%4");
+        assert_eq!(percent_decode_plus(b"%zz%41"), "%zzA");
+        assert_eq!(percent_decode_plus(b"%%41"), "%A");
+        assert_eq!(percent_decode_plus(b"a+%2B+b"), "a + b");
+        assert_eq!(percent_decode_plus(b"%FF"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn header_parameters_are_parsed() {
+        assert_eq!(
+            parse_params(" Name=\"a;b\" ; filename=\"x\\\"y\"junk; flag; Size=3 "),
+            vec![
+                ("name".to_string(), "a;b".to_string()),
+                ("filename".to_string(), "x\"y".to_string()),
+                ("flag".to_string(), String::new()),
+                ("size".to_string(), "3".to_string()),
+            ]
+        );
+        assert!(parse_params("").is_empty());
+        assert!(parse_params(";;").is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use javy_plugin_api::javy::quickjs::{Array, Ctx, Object, String as JSString, Value};
+use std::collections::HashMap;
+
+/// An ordered header list, names may repeat
+pub type HeaderList = Vec<(String, String)>;
+
+/// Parse request headers from an array of `[name, value]` pairs (as sent by `Headers`)
+/// or from a plain object. Order and repeated names are kept.
+pub fn parse_headers(obj: &Object) -> Result<HeaderList> {
+    let mut headers = Vec::new();
+
+    if let Some(pairs) = obj.as_array() {
+        for pair in pairs.iter::<Array>() {
+            let pair = pair.map_err(|_| anyhow!("header entries must be [name, value] pairs"))?;
+            let name: String = pair.get(0)?;
+            let value: String = pair.get(1)?;
+            headers.push((name, value));
+        }
+        return Ok(headers);
+    }
+
+    for key in obj.keys::<String>().flatten() {
+        if let Ok(Some(value)) = obj.get::<_, Option<String>>(&key) {
+            headers.push((key, value));
+        }
+    }
+
+    Ok(headers)
+}
+
+/// Get a request header, joining repeated values with `, `
+pub fn request_header(headers: &[(String, String)], name: &str) -> Option<String> {
+    let values: Vec<&str> = headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+        .collect();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// Whether a header is present, ignoring case
+pub fn has_header(headers: &[(String, String)], name: &str) -> bool {
+    headers
+        .iter()
+        .any(|(key, _)| key.eq_ignore_ascii_case(name))
+}
+
+/// Remove every header with one of the given names, ignoring case
+pub fn remove_headers(headers: &mut HeaderList, names: &[&str]) {
+    headers.retain(|(key, _)| !names.iter().any(|name| key.eq_ignore_ascii_case(name)));
+}
+
+/// Combine repeated headers into a single value per name, in order of first appearance.
+/// The host takes one value per header name, so values are joined with `, `
+/// (or `; ` for `Cookie`, which does not allow comma separated values).
+pub fn combine_headers(headers: &[(String, String)]) -> HeaderList {
+    let mut combined: HeaderList = Vec::new();
+    for (name, value) in headers {
+        match combined
+            .iter_mut()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        {
+            Some((key, existing)) => {
+                existing.push_str(if key.eq_ignore_ascii_case("cookie") {
+                    "; "
+                } else {
+                    ", "
+                });
+                existing.push_str(value);
+            }
+            None => combined.push((name.clone(), value.clone())),
+        }
+    }
+    combined
+}
+
+/// Convert response headers into an array of lowercase `[name, value]` pairs.
+/// `Set-Cookie` headers the host folded into one value are split back into separate entries.
+pub fn response_headers_to_js<'js>(
+    cx: Ctx<'js>,
+    headers: &HashMap<String, String>,
+) -> Result<Array<'js>> {
+    let mut list: HeaderList = Vec::new();
+    for (name, value) in headers {
+        let name = name.to_ascii_lowercase();
+        if name == "set-cookie" {
+            list.extend(
+                split_set_cookie(value)
+                    .into_iter()
+                    .map(|cookie| (name.clone(), cookie)),
+            );
+        } else {
+            list.push((name, value.clone()));
+        }
+    }
+
+    let array = Array::new(cx.clone())?;
+    for (i, (name, value)) in list.into_iter().enumerate() {
+        let pair = Array::new(cx.clone())?;
+        pair.set(0, JSString::from_str(cx.clone(), &name)?)?;
+        pair.set(1, JSString::from_str(cx.clone(), &value)?)?;
+        array.set(i, Value::from_array(pair))?;
+    }
+    Ok(array)
+}
+
+/// Split a folded `Set-Cookie` value into individual cookies.
+/// Cookies are separated by newlines or by commas that start a new `name=value` pair,
+/// so commas inside attributes such as `Expires=Wed, 21 Oct 2015 07:28:00 GMT` are kept.
+pub fn split_set_cookie(value: &str) -> Vec<String> {
+    let mut cookies = Vec::new();
+    for line in value.split('\n') {
+        let mut start = 0;
+        for (i, _) in line.match_indices(',') {
+            if starts_cookie_pair(&line[i + 1..]) {
+                cookies.push(line[start..i].trim().to_string());
+                start = i + 1;
+            }
+        }
+        cookies.push(line[start..].trim().to_string());
+    }
+    cookies.retain(|cookie| !cookie.is_empty());
+    cookies
+}
+
+/// Whether the text after a comma looks like the `name=` start of a new cookie
+fn starts_cookie_pair(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    match rest.find('=') {
+        Some(end) => {
+            let name = &rest[..end];
+            !name.is_empty()
+                && !name
+                    .chars()
+                    .any(|c| c.is_ascii_whitespace() || matches!(c, ';' | ','))
+        }
+        None => false,
+    }
+}
+
+/// Parse an IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) into milliseconds since the Unix epoch
+pub fn parse_http_date(value: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let mut parts = value.split_whitespace();
+    parts.next()?.strip_suffix(',')?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if time.next().is_some() || parts.next()? != "GMT" || parts.next().is_some() {
+        return None;
+    }
+    // A leap second is written as second 60
+    if !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    Some(unix_time_ms(year, month, day, (hours, minutes, seconds)))
+}
+
+/// Milliseconds since the Unix epoch of a UTC date and time in the proleptic Gregorian calendar
+pub fn unix_time_ms(
+    year: i64,
+    month: i64,
+    day: i64,
+    (hours, minutes, seconds): (i64, i64, i64),
+) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    ((days * 24 + hours) * 60 + minutes) * 60_000 + seconds * 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderList {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn folded_set_cookie_values_are_split() {
+        assert_eq!(
+            split_set_cookie(
+                "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Path=/, b=2; Max-Age=60,c=3"
+            ),
+            vec![
+                "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Path=/",
+                "b=2; Max-Age=60",
+                "c=3",
+            ]
+        );
+        assert_eq!(
+            split_set_cookie("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT\nb=2\n\n"),
+            vec!["a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT", "b=2"]
+        );
+    }
+
+    #[test]
+    fn commas_that_do_not_start_a_cookie_are_kept() {
+        assert_eq!(split_set_cookie("a=x,y"), vec!["a=x,y"]);
+        assert_eq!(
+            split_set_cookie("a=1; Comment=one, two=three words"),
+            vec!["a=1; Comment=one", "two=three words"]
+        );
+        assert_eq!(split_set_cookie("a=1, ; b=2"), vec!["a=1, ; b=2"]);
+        assert!(split_set_cookie("\n \n").is_empty());
+    }
+
+    #[test]
+    fn repeated_headers_are_combined() {
+        let combined = combine_headers(&headers(&[
+            ("Accept", "text/html"),
+            ("Cookie", "a=1"),
+            ("accept", "application/json"),
+            ("X-Id", "7"),
+            ("cookie", "b=2"),
+        ]));
+        assert_eq!(
+            combined,
+            headers(&[
+                ("Accept", "text/html, application/json"),
+                ("Cookie", "a=1; b=2"),
+                ("X-Id", "7"),
+            ])
+        );
+    }
+
+    #[test]
+    fn http_dates_are_parsed() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784_111_777_000)
+        );
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(
+            parse_http_date("Tue, 29 Feb 2000 23:59:60 GMT"),
+            Some(951_868_800_000)
+        );
+        assert_eq!(
+            parse_http_date("Wed, 31 Dec 1969 23:59:59 GMT"),
+            Some(-1000)
+        );
+    }
+
+    #[test]
+    fn invalid_http_dates_are_rejected() {
+        for date in [
+            "",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 06 Nov 1994 08:49:37 GMT extra",
+            "Sun, 06 nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 08:49 GMT",
+            "Sun, 06 Nov 1994 08:49:37:00 GMT",
+            "Sun, 32 Nov 1994 08:49:37 GMT",
+            "Sun, 00 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:60:00 GMT",
+            "Sun, 06 Nov 1994 08:49:61 GMT",
+        ] {
+            assert_eq!(parse_http_date(date), None, "{date}");
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use super::error::{FetchError, FetchErrorKind};
+
+/// Hash algorithms accepted in integrity metadata, from weakest to strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Algorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(Self::Sha256),
+            "sha384" => Some(Self::Sha384),
+            "sha512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn digest(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(bytes).to_vec(),
+            Self::Sha384 => Sha384::digest(bytes).to_vec(),
+            Self::Sha512 => Sha512::digest(bytes).to_vec(),
+        }
+    }
+}
+
+/// Parse integrity metadata (`sha384-<base64> sha512-<base64>`) into algorithm and digest pairs.
+/// Unknown algorithms and malformed entries are ignored, as Subresource Integrity specifies.
+fn parse_metadata(integrity: &str) -> Vec<(Algorithm, Vec<u8>)> {
+    integrity
+        .split_ascii_whitespace()
+        .filter_map(|token| {
+            // Options after `?` are reserved and have no meaning yet
+            let token = token.split('?').next().unwrap_or(token);
+            let (algorithm, digest) = token.split_once('-')?;
+            let algorithm = Algorithm::parse(algorithm)?;
+            let digest = STANDARD.decode(digest).ok()?;
+            Some((algorithm, digest))
+        })
+        .collect()
+}
+
+/// Check a response body against the request's integrity metadata.
+///
+/// Only the strongest algorithm listed is used, and the body matches when any digest for it does.
+/// Metadata without a supported algorithm does not restrict the response.
+pub fn verify(url: &str, integrity: &str, body: &[u8]) -> Result<(), FetchError> {
+    let metadata = parse_metadata(integrity);
+    let Some(strongest) = metadata.iter().map(|(algorithm, _)| *algorithm).max() else {
+        return Ok(());
+    };
+
+    let actual = strongest.digest(body);
+    if metadata
+        .iter()
+        .any(|(algorithm, digest)| *algorithm == strongest && *digest == actual)
+    {
+        Ok(())
+    } else {
+        Err(FetchError::new(
+            FetchErrorKind::InvalidResponse,
+            "ERR_INTEGRITY",
+            format!(
+                "Failed to fetch {}: the response body does not match the integrity metadata",
+                url
+            ),
+        ))
+    }
+}
+
+/// Whether integrity metadata names an algorithm that can be checked
+pub fn is_checked(integrity: &str) -> bool {
+    !parse_metadata(integrity).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256_ABC: &str = "sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=";
+    const SHA384_ABC: &str =
+        "sha384-ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP/W+2AhgcroefMI1i67KE0yCWn";
+
+    #[test]
+    fn matching_bodies_pass() {
+        assert!(verify("http://a/", SHA256_ABC, b"abc").is_ok());
+        assert!(verify("http://a/", &format!("{}?opt", SHA384_ABC), b"abc").is_ok());
+    }
+
+    #[test]
+    fn other_bodies_fail() {
+        let error = verify("http://a/", SHA256_ABC, b"abd").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("does not match the integrity metadata"));
+    }
+
+    #[test]
+    fn only_the_strongest_algorithm_is_used() {
+        // A matching SHA-256 digest does not make up for a wrong SHA-384 one
+        let integrity = format!("{} sha384-AAAA", SHA256_ABC);
+        assert!(verify("http://a/", &integrity, b"abc").is_err());
+        let integrity = format!("sha384-AAAA {}", SHA384_ABC);
+        assert!(verify("http://a/", &integrity, b"abc").is_ok());
+    }
+
+    #[test]
+    fn unsupported_metadata_does_not_restrict() {
+        assert!(!is_checked("md5-kAFQmDzST7DWlj99KOF/cg== sha256 sha256-!!"));
+        assert!(verify("http://a/", "md5-kAFQmDzST7DWlj99KOF/cg==", b"abc").is_ok());
+        assert!(is_checked(&format!("SHA256-{}", &SHA256_ABC[7..])));
+    }
+}
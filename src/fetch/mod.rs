@@ -2,13 +2,66 @@ use anyhow::{anyhow, Result};
 use blockless_sdk::http::{HttpClient, HttpResponse, MultipartField};
 use javy_plugin_api::javy::{
     hold_and_release,
-    quickjs::{prelude::MutFn, Function, Object, String as JSString, Value},
+    quickjs::{
+        class::{ClassId, JsClass, Trace, Tracer, Writable},
+        function::Constructor,
+        prelude::MutFn,
+        Array, Class, Ctx, Exception, Function, Object, String as JSString, TypedArray, Value,
+    },
     to_js_error, Args,
 };
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+mod blob;
+mod body;
+mod cache;
+mod cookies;
+mod decode;
+mod error;
+mod event_stream;
+mod form;
+mod headers;
+mod integrity;
+mod retry;
+mod upload;
+
+use blob::BlobData;
+pub use blob::{
+    bless_blob_create, bless_blob_read, bless_blob_size, bless_blob_slice, bless_blob_text,
+};
+use body::{BodyReader, ChunkedRequest};
+use cache::CacheMode;
+pub use cache::{bless_fetch_cache_clear, bless_fetch_cache_persist};
+pub use cookies::{bless_cookie_jar_clear, bless_cookie_jar_enable, bless_cookie_jar_list};
+use error::{throw_fetch_error, FetchError, FetchErrorKind};
+pub use event_stream::{
+    bless_event_stream_create, bless_event_stream_parse, bless_event_stream_retry,
+};
+pub use form::bless_form_data_parse;
+use headers::{
+    combine_headers, has_header, parse_headers, remove_headers, response_headers_to_js, HeaderList,
+};
+use retry::RetryPolicy;
+use upload::UploadData;
+pub use upload::{bless_upload_create, bless_upload_from_fd, bless_upload_write};
+
+/// Maximum number of redirects followed by default, as in the fetch spec
+const MAX_REDIRECTS: u32 = 20;
 
-/// A fetch-compliant HTTP client
-pub fn bless_fetch_request(args: Args<'_>) -> Result<Value<'_>> {
+/// Largest `chunkSize` accepted, `Number.MAX_SAFE_INTEGER`, so that range ends never overflow
+const MAX_CHUNK_SIZE: f64 = 9_007_199_254_740_991.0;
+
+/// Start a fetch request.
+/// The URL and options are validated right away and a native request handle is returned.
+/// The request is sent once it is completed with `bless_fetch_complete`, from the event loop.
+/// Starting requests does not make them concurrent, see `bless_fetch_complete`.
+pub fn bless_fetch_start(args: Args<'_>) -> Result<Value<'_>> {
     let (cx, args) = args.release();
 
     // Parse URL and options from JavaScript
@@ -32,62 +85,454 @@ pub fn bless_fetch_request(args: Args<'_>) -> Result<Value<'_>> {
         FetchOptions::default()
     };
 
+    Ok(Class::instance(cx, PendingRequest(Some((url, options))))?.into_value())
+}
+
+/// Complete a started fetch request, following redirects and returning the response.
+/// The host call blocks until the response arrives and the host has no batch call, so the
+/// requests completed in one turn of the event loop are sent one after another.
+pub fn bless_fetch_complete(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let request = args
+        .first()
+        .and_then(Value::as_object)
+        .and_then(Class::<PendingRequest>::from_object)
+        .ok_or_else(|| anyhow!("fetch_complete: invalid request handle"))?;
+    let (url, options) = request
+        .borrow_mut()
+        .0
+        .take()
+        .ok_or_else(|| anyhow!("fetch_complete: the request was already completed"))?;
+    send_fetch(cx.clone(), url, options).map_err(|e| throw_fetch_error(&cx, e))
+}
+
+/// Send a request, following redirects as the options ask
+fn send_fetch(cx: Ctx<'_>, mut url: String, options: FetchOptions) -> Result<Value<'_>> {
+    let request_url = url.clone();
+    let mut method = options.method.to_uppercase();
+    let mut headers = options.headers.clone();
+    let mut body = options.body.clone();
+    let mut redirects = 0;
+
+    // GET bodies are requested in chunks when the caller sets `chunkSize`, unless it asked for a range.
+    // Bodies checked against integrity metadata are read whole, they cannot be used before the check.
+    let checked = integrity::is_checked(&options.integrity);
+    let chunked =
+        method == "GET" && options.chunk_size > 0 && !checked && !has_header(&headers, "range");
+    if chunked {
+        headers.push((
+            "range".to_string(),
+            format!("bytes=0-{}", options.chunk_size - 1),
+        ));
+    }
+
+    // Ask for compressed bodies, which are decoded as they are read
+    if options.decompress && !has_header(&headers, "accept-encoding") {
+        headers.push((
+            "accept-encoding".to_string(),
+            decode::ACCEPT_ENCODING.to_string(),
+        ));
+    }
+
+    // Only full GET responses are cached, other requests bypass the cache. So do requests the
+    // cookie jar adds cookies to, since the cache cannot tell responses for different cookies apart.
+    let jar_cookies = options.credentials.allows(&request_url, &request_url)
+        && cookies::cookie_header(&request_url).is_some();
+    let cache_mode = if jar_cookies {
+        CacheMode::NoStore
+    } else {
+        options.cache.for_request(&method, &options.headers)
+    };
+    headers.extend(options.cache.request_headers(&headers));
+    let cached = match cache_mode {
+        CacheMode::NoStore | CacheMode::Reload => None,
+        _ => cache::lookup(&request_url, &options.headers),
+    };
+    if cache_mode == CacheMode::OnlyIfCached && cached.is_none() {
+        return Err(FetchError::new(
+            FetchErrorKind::Network,
+            "ERR_CACHE_MISS",
+            format!(
+                "Failed to fetch {}: the response is not cached and the cache mode is 'only-if-cached'",
+                request_url
+            ),
+        )
+        .into());
+    }
+    // Stored responses that cannot be used as they are get revalidated, if they have validators
+    let (hit, revalidating) = match cached {
+        Some(entry)
+            if matches!(cache_mode, CacheMode::ForceCache | CacheMode::OnlyIfCached)
+                || (cache_mode == CacheMode::Default && entry.is_fresh()) =>
+        {
+            (Some(entry), None)
+        }
+        Some(entry) if entry.has_validators() => (None, Some(entry)),
+        _ => (None, None),
+    };
+    if let Some(entry) = &revalidating {
+        headers.extend(entry.conditional_headers());
+    }
+
+    let (response, final_url, redirected, response_type) = match &hit {
+        Some(entry) => {
+            // Stored responses set their cookies again, as they would coming from the network
+            if options.credentials.allows(&request_url, &entry.url) {
+                cookies::store_response_cookies(&entry.url, &entry.headers);
+            }
+            (
+                entry.to_response(),
+                entry.url.clone(),
+                entry.redirected,
+                "basic",
+            )
+        }
+        None => loop {
+            let send_cookies = options.credentials.allows(&request_url, &url);
+            let hop_headers = headers_with_cookies(&headers, &url, send_cookies);
+            let response = send_with_retry(&url, &method, &hop_headers, body.as_deref(), &options)?;
+            if send_cookies {
+                cookies::store_response_cookies(&url, &response.headers);
+            }
+
+            // The host reports the URL it ended up at, which differs when it followed redirects itself
+            let final_url = if response.url.is_empty() {
+                url.clone()
+            } else {
+                response.url.clone()
+            };
+            let host_redirected = !same_url(&final_url, &url);
+            let location = if is_redirect_status(response.status) {
+                header_value(&response.headers, "location")
+            } else {
+                None
+            };
+
+            if location.is_none() && !host_redirected {
+                break (response, final_url, redirects > 0, "basic");
+            }
+
+            match options.redirect {
+                RedirectMode::Error => {
+                    return Err(FetchError::new(
+                        FetchErrorKind::InvalidRequest,
+                        "ERR_REDIRECT",
+                        format!(
+                            "Failed to fetch {}: redirect mode is set to 'error'",
+                            request_url
+                        ),
+                    )
+                    .into());
+                }
+                RedirectMode::Manual => {
+                    // Opaque-redirect responses expose neither the status, headers nor body
+                    let response = HttpResponse {
+                        status: 0,
+                        headers: HashMap::new(),
+                        body: Vec::new(),
+                        url: url.clone(),
+                    };
+                    break (response, url, false, "opaqueredirect");
+                }
+                RedirectMode::Follow => {}
+            }
+
+            let Some(location) = location else {
+                // The host already followed the redirects, this is the final response
+                break (response, final_url, true, "basic");
+            };
+
+            if redirects >= options.max_redirects {
+                return Err(FetchError::new(
+                    FetchErrorKind::Network,
+                    "ERR_TOO_MANY_REDIRECTS",
+                    format!("Failed to fetch {}: too many redirects", request_url),
+                )
+                .into());
+            }
+            let next = Url::parse(&final_url)
+                .and_then(|base| base.join(&location))
+                .ok()
+                .filter(|next| matches!(next.scheme(), "http" | "https"))
+                .ok_or_else(|| {
+                    FetchError::new(
+                        FetchErrorKind::InvalidResponse,
+                        "ERR_INVALID_REDIRECT",
+                        format!(
+                            "Failed to fetch {}: invalid redirect location '{}'",
+                            request_url, location
+                        ),
+                    )
+                })?;
+
+            // 303 responses, and 301/302 responses to a POST, are followed with a GET without a body
+            if (response.status == 303 && method != "HEAD")
+                || (matches!(response.status, 301 | 302) && method == "POST")
+            {
+                method = "GET".to_string();
+                body = None;
+                remove_headers(
+                    &mut headers,
+                    &[
+                        "content-type",
+                        "content-length",
+                        "content-encoding",
+                        "content-language",
+                    ],
+                );
+            }
+
+            // Credentials are not forwarded to another origin
+            if !same_origin(&final_url, next.as_str()) {
+                remove_headers(&mut headers, &["authorization"]);
+            }
+
+            url = next.to_string();
+            redirects += 1;
+        },
+    };
+
+    // A `304 Not Modified` to a revalidation means the stored response is still current
+    let (response, from_cache) = match revalidating {
+        Some(entry) if response_type == "basic" && response.status == 304 => (
+            cache::revalidated(&request_url, entry, &response).to_response(),
+            true,
+        ),
+        _ => (response, hit.is_some()),
+    };
+
+    // Requests that may change the resource make the stored response outdated
+    if !matches!(method.as_str(), "GET" | "HEAD" | "OPTIONS" | "TRACE") && response.status < 400 {
+        cache::invalidate(&request_url);
+        cache::invalidate(&final_url);
+    }
+
+    let (mut response, reader) = if from_cache {
+        let mut response = response;
+        let body = std::mem::take(&mut response.body);
+        (response, BodyReader::complete(body))
+    } else if chunked && response_type == "basic" && response.status == 416 {
+        // Empty resources cannot satisfy the first range, ask for the whole response instead
+        remove_headers(&mut headers, &["range"]);
+        let send_cookies = options.credentials.allows(&request_url, &final_url);
+        let headers = headers_with_cookies(&headers, &final_url, send_cookies);
+        let mut response = send_with_retry(&final_url, &method, &headers, None, &options)?;
+        let body = std::mem::take(&mut response.body);
+        (response, BodyReader::complete(body))
+    } else if chunked && response_type == "basic" {
+        BodyReader::start(
+            response,
+            ChunkedRequest {
+                url: final_url.clone(),
+                headers: headers_with_cookies(
+                    &headers,
+                    &final_url,
+                    options.credentials.allows(&request_url, &final_url),
+                ),
+                options: options.clone(),
+                chunk_size: options.chunk_size,
+            },
+        )
+    } else {
+        let mut response = response;
+        let body = std::mem::take(&mut response.body);
+        (response, BodyReader::complete(body))
+    };
+
+    // Bodies that were received in full can be stored
+    if !from_cache && response_type == "basic" && cache_mode != CacheMode::NoStore {
+        if let Some(body) = reader.complete_body() {
+            cache::store(
+                &request_url,
+                &options.headers,
+                &response,
+                body,
+                &final_url,
+                redirected,
+            );
+        }
+    }
+
+    // Compressed bodies are decoded unless the caller asked for them as received
+    let reader = if options.decompress {
+        reader.decode(&mut response)?
+    } else {
+        reader
+    };
+
+    // Integrity covers the decoded body
+    if checked {
+        // Opaque responses expose no body to check
+        if response_type != "basic" {
+            return Err(FetchError::new(
+                FetchErrorKind::InvalidResponse,
+                "ERR_INTEGRITY",
+                format!(
+                    "Failed to fetch {}: integrity cannot be checked on an opaque response",
+                    request_url
+                ),
+            )
+            .into());
+        }
+        integrity::verify(
+            &request_url,
+            &options.integrity,
+            reader.complete_body().unwrap_or_default(),
+        )?;
+    }
+
+    // Create JavaScript Response object
+    create_js_response(cx, response, final_url, redirected, response_type, reader)
+}
+
+/// Request headers for `url`, with the cookie jar's cookies for it when they may be sent
+fn headers_with_cookies(headers: &[(String, String)], url: &str, send_cookies: bool) -> HeaderList {
+    let mut headers = headers.to_vec();
+    if let Some(cookie) = send_cookies.then(|| cookies::cookie_header(url)).flatten() {
+        headers.push(("cookie".to_string(), cookie));
+    }
+    headers
+}
+
+/// Get the host timeout for the next request, failing if the signal was aborted
+fn request_timeout(options: &FetchOptions) -> Result<Option<u32>> {
+    let mut timeout = options.timeout;
+    if let Some(signal) = &options.signal {
+        // An already aborted signal stops the request before it reaches the host
+        if signal.aborted {
+            return Err(FetchError::aborted().into());
+        }
+        // Cap the host timeout by the time left on a timeout signal
+        if let Some(remaining) = signal.remaining_ms() {
+            if remaining == 0 {
+                return Err(FetchError::timed_out().into());
+            }
+            timeout = Some(timeout.map_or(remaining, |t| t.min(remaining)));
+        }
+    }
+    Ok(timeout)
+}
+
+/// Send a request, retrying transient failures and retryable statuses as the `retry` option asks
+fn send_with_retry(
+    url: &str,
+    method: &str,
+    headers: &[(String, String)],
+    body: Option<&BodyInit>,
+    options: &FetchOptions,
+) -> Result<HttpResponse> {
+    let policy = options
+        .retry
+        .as_ref()
+        .filter(|policy| policy.applies_to(method, headers));
+    let mut attempt = 0;
+    loop {
+        let timeout = request_timeout(options)?;
+        let result = send_request(url, method, headers, body, timeout);
+        let delay = match (&result, policy) {
+            (Ok(response), Some(policy)) => policy.delay_after_response(attempt, response),
+            (Err(error), Some(policy)) => policy.delay_after_error(attempt, error),
+            (_, None) => None,
+        };
+        let Some(delay) = delay else {
+            return result;
+        };
+
+        // Waiting past the signal's deadline would only end in a timeout
+        let remaining = options.signal.as_ref().and_then(AbortSignal::remaining_ms);
+        if remaining.is_some_and(|remaining| u64::from(remaining) <= delay) {
+            return Err(FetchError::timed_out().into());
+        }
+        std::thread::sleep(Duration::from_millis(delay));
+        attempt += 1;
+    }
+}
+
+/// Send a single request to the host without any redirect handling.
+/// The SDK takes the body by value, so it is copied for the duration of the host call.
+fn send_request(
+    url: &str,
+    method: &str,
+    headers: &[(String, String)],
+    body: Option<&BodyInit>,
+    timeout: Option<u32>,
+) -> Result<HttpResponse> {
     // Create HTTP client
     let client = HttpClient::new();
 
     // Build request
-    let mut request = match options.method.to_uppercase().as_str() {
-        "GET" => client.get(&url),
-        "POST" => client.post(&url),
-        "PUT" => client.put(&url),
-        "PATCH" => client.patch(&url),
-        "DELETE" => client.delete(&url),
-        "HEAD" => client.head(&url),
-        method => client.request(method, &url),
+    let mut request = match method {
+        "GET" => client.get(url),
+        "POST" => client.post(url),
+        "PUT" => client.put(url),
+        "PATCH" => client.patch(url),
+        "DELETE" => client.delete(url),
+        "HEAD" => client.head(url),
+        method => client.request(method, url),
     };
 
     // Add headers
-    for (key, value) in options.headers {
+    let has_content_type = has_header(headers, "content-type");
+    for (key, value) in combine_headers(headers) {
         request = request.header(key, value);
     }
 
     // Add body
-    if let Some(body) = options.body {
+    if let Some(body) = body {
         match body {
-            BodyInit::Text(text) => request = request.body(text),
+            BodyInit::Text(text) => request = request.body(text.as_str()),
             BodyInit::Json(json) => {
-                request = request.header("Content-Type", "application/json");
-                request = request.body(json);
-            }
-            BodyInit::FormData(form_data) => {
-                let fields: Vec<MultipartField> = form_data
-                    .into_iter()
-                    .map(|(name, value)| MultipartField::text(name, value))
-                    .collect();
-                request = request.multipart(fields);
+                if !has_content_type {
+                    request = request.header("Content-Type", "application/json");
+                }
+                request = request.body(json.as_str());
             }
-            BodyInit::Binary(data) => request = request.body_bytes(data),
+            BodyInit::FormData(fields) => request = request.multipart(fields.clone()),
+            BodyInit::Binary(data) => request = request.body_bytes(data.clone()),
         }
     }
 
     // Set timeout if specified
-    if let Some(timeout) = options.timeout {
+    if let Some(timeout) = timeout {
         request = request.timeout(timeout);
     }
 
-    // Add signal support (AbortController)
-    if let Some(_signal) = options.signal {
-        // Note: AbortController support would require additional implementation
-        // For now, we'll ignore it but this is where it would be handled
-    }
-
     // Execute request
-    let response = request
+    request
         .send()
-        .map_err(|e| anyhow!("Request failed: {:?}", e))?;
+        .map_err(|e| FetchError::from_http(url, e).into())
+}
 
-    // Create JavaScript Response object
-    create_js_response(cx, response, url)
+fn is_redirect_status(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// Look up a response header by name, ignoring case
+fn header_value(headers: &HashMap<String, String>, name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+}
+
+/// Whether two URLs have the same scheme, host and port
+fn same_origin(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// Compare two URLs ignoring their fragments and differences in serialization
+fn same_url(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(mut a), Ok(mut b)) => {
+            a.set_fragment(None);
+            b.set_fragment(None);
+            a == b
+        }
+        _ => a == b,
+    }
 }
 
 /// Parse fetch options from JavaScript object
@@ -106,7 +551,7 @@ fn parse_fetch_options(obj: &Object) -> Result<FetchOptions> {
 
     // Body
     if let Ok(Some(body_value)) = obj.get::<_, Option<Value>>("body") {
-        options.body = Some(parse_body(&body_value)?);
+        options.body = Some(Rc::new(parse_body(&body_value)?));
     }
 
     // Mode
@@ -116,17 +561,67 @@ fn parse_fetch_options(obj: &Object) -> Result<FetchOptions> {
 
     // Credentials
     if let Ok(Some(credentials)) = obj.get::<_, Option<String>>("credentials") {
-        options.credentials = credentials;
+        options.credentials = Credentials::parse(&credentials).ok_or_else(|| {
+            Exception::throw_type(
+                obj.ctx(),
+                &format!(
+                    "'{}' is not a valid credentials mode, expected 'omit', 'same-origin' or 'include'",
+                    credentials
+                ),
+            )
+        })?;
     }
 
     // Cache
     if let Ok(Some(cache)) = obj.get::<_, Option<String>>("cache") {
-        options.cache = cache;
+        options.cache = CacheMode::parse(&cache).ok_or_else(|| {
+            Exception::throw_type(
+                obj.ctx(),
+                &format!(
+                    "'{}' is not a valid cache mode, expected 'default', 'no-store', 'reload', 'no-cache', 'force-cache' or 'only-if-cached'",
+                    cache
+                ),
+            )
+        })?;
     }
 
     // Redirect
     if let Ok(Some(redirect)) = obj.get::<_, Option<String>>("redirect") {
-        options.redirect = redirect;
+        options.redirect = RedirectMode::parse(&redirect).ok_or_else(|| {
+            Exception::throw_type(
+                obj.ctx(),
+                &format!(
+                    "'{}' is not a valid redirect mode, expected 'follow', 'error' or 'manual'",
+                    redirect
+                ),
+            )
+        })?;
+    }
+
+    // Decode compressed response bodies, `false` returns them as received (non-standard)
+    if let Ok(Some(decompress)) = obj.get::<_, Option<bool>>("decompress") {
+        options.decompress = decompress;
+    }
+
+    // Stream a GET response body with range requests of this size (non-standard).
+    // Unset or 0 reads it with a single request.
+    if let Ok(Some(chunk_size)) = obj.get::<_, Option<f64>>("chunkSize") {
+        if !(0.0..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+            return Err(Exception::throw_range(
+                obj.ctx(),
+                &format!(
+                    "chunkSize must be a number of bytes from 0 to {}",
+                    MAX_CHUNK_SIZE
+                ),
+            )
+            .into());
+        }
+        options.chunk_size = chunk_size as u64;
+    }
+
+    // Maximum number of redirects to follow (non-standard)
+    if let Ok(Some(max_redirects)) = obj.get::<_, Option<f64>>("maxRedirects") {
+        options.max_redirects = max_redirects as u32;
     }
 
     // Referrer
@@ -150,9 +645,11 @@ fn parse_fetch_options(obj: &Object) -> Result<FetchOptions> {
     }
 
     // Signal (AbortSignal)
-    if let Ok(Some(_signal)) = obj.get::<_, Option<Object>>("signal") {
-        // Store signal for potential abort handling
-        options.signal = Some(AbortSignal {});
+    if let Ok(Some(signal)) = obj.get::<_, Option<Object>>("signal") {
+        options.signal = Some(AbortSignal {
+            aborted: signal.get::<_, Option<bool>>("aborted")?.unwrap_or(false),
+            deadline: signal.get::<_, Option<f64>>("_deadline")?,
+        });
     }
 
     // Timeout (non-standard but useful)
@@ -160,27 +657,12 @@ fn parse_fetch_options(obj: &Object) -> Result<FetchOptions> {
         options.timeout = Some(timeout as u32);
     }
 
-    Ok(options)
-}
-
-/// Parse headers from JavaScript object or Headers instance
-fn parse_headers(obj: &Object) -> Result<HashMap<String, String>> {
-    let mut headers = HashMap::new();
-
-    // Try to iterate over object properties
-    // This is a simplified version - in a real implementation we'd need to handle
-    // Headers instances and arrays of [key, value] pairs
-    let keys_iter = obj.keys::<String>();
-
-    for key_result in keys_iter {
-        if let Ok(key) = key_result {
-            if let Ok(Some(value)) = obj.get::<_, Option<String>>(&key) {
-                headers.insert(key, value);
-            }
-        }
+    // Retry (non-standard), for transient failures and statuses such as 503
+    if let Ok(retry) = obj.get::<_, Value>("retry") {
+        options.retry = RetryPolicy::parse(&retry)?;
     }
 
-    Ok(headers)
+    Ok(options)
 }
 
 /// Parse body from JavaScript value
@@ -193,91 +675,125 @@ fn parse_body(value: &Value) -> Result<BodyInit> {
     } else if let Some(obj) = value.as_object() {
         // Check if it's FormData
         if let Ok(Some(true)) = obj.get::<_, Option<bool>>("_isFormData") {
-            let form_data = HashMap::new();
-            // Parse FormData entries - simplified for now
-            // In a real implementation, we'd properly parse the FormData structure
-            Ok(BodyInit::FormData(form_data))
-        } else if let Ok(Some(constructor_name)) = obj.get::<_, Option<String>>("constructor.name")
-        {
-            // Check if it's an ArrayBuffer or typed array
-            if constructor_name == "ArrayBuffer" || constructor_name.ends_with("Array") {
-                return extract_binary_data(obj);
-            } else {
-                // Assume it's JSON - serialize the object manually
-                // In a real implementation, we'd need to properly serialize the JS object
-                Ok(BodyInit::Json("{}".to_string()))
-            }
-        } else {
-            // Try to check for ArrayBuffer or Uint8Array by checking for byteLength property
-            if obj.get::<_, Option<f64>>("byteLength").is_ok() {
-                return extract_binary_data(obj);
-            }
-            // Assume it's JSON - serialize the object manually
-            // In a real implementation, we'd need to properly serialize the JS object
-            Ok(BodyInit::Json("{}".to_string()))
-        }
-    } else if value.is_array() {
-        // Handle arrays - check if it's a typed array or regular array
-        if let Some(array_obj) = value.as_object() {
-            extract_binary_data(array_obj)
+            Ok(BodyInit::FormData(parse_form_data(obj)?))
+        } else if let Some(blob) = BlobData::from_object(obj) {
+            // Blob handles are sent as their raw bytes
+            Ok(BodyInit::Binary(blob.bytes().to_vec()))
+        } else if let Some(bytes) = UploadData::take_from_object(obj) {
+            // Streamed bodies and files, already collected in Rust memory
+            Ok(BodyInit::Binary(bytes))
+        } else if let Some(bytes) = read_binary_data(obj)? {
+            // ArrayBuffer, typed array or DataView
+            Ok(BodyInit::Binary(bytes))
         } else {
-            Err(anyhow!("Invalid array body"))
+            // Plain objects and arrays are sent as JSON
+            Ok(BodyInit::Json(serialize_json(value)?))
         }
     } else {
         Err(anyhow!("Unsupported body type"))
     }
 }
 
-/// Extract binary data from ArrayBuffer or typed arrays
-fn extract_binary_data(obj: &Object) -> Result<BodyInit> {
-    let mut bytes = Vec::new();
+/// Parse the `_entries` of a FormData body into multipart fields.
+/// Each entry is `[name, value, filename, type]`. String values become text fields,
+/// blob handles and binary data become file parts. Entry order, including duplicate names, is kept.
+fn parse_form_data(obj: &Object) -> Result<Vec<MultipartField>> {
+    let Some(entries) = obj.get::<_, Option<Array>>("_entries")? else {
+        return Ok(Vec::new());
+    };
 
-    // Check if it's an ArrayBuffer
-    if let Ok(Some(byte_length)) = obj.get::<_, Option<f64>>("byteLength") {
-        let length = byte_length as usize;
+    let mut fields = Vec::with_capacity(entries.len());
+    for entry in entries.iter::<Array>() {
+        let entry = entry.map_err(|_| anyhow!("FormData entry must be an array"))?;
+        let name: String = entry
+            .get(0)
+            .map_err(|_| anyhow!("FormData entry name must be a string"))?;
+        let value: Value = entry.get(1)?;
+        let filename: Option<String> = entry.get(2)?;
+        let content_type = entry
+            .get::<Option<String>>(3)?
+            .filter(|content_type| !content_type.is_empty());
 
-        // For ArrayBuffer, we need to check if there's a way to access the raw bytes
-        // First, try to see if it's exposed as an array-like object
-        for i in 0..length {
-            if let Ok(Some(byte)) = obj.get::<_, Option<f64>>(&i.to_string()) {
-                bytes.push(byte as u8);
-            } else {
-                // If we can't access bytes directly, try alternative methods
-                break;
-            }
-        }
-        if bytes.len() == length {
-            return Ok(BodyInit::Binary(bytes));
-        }
+        let field = if let Some(s) = value.as_string() {
+            let text = s
+                .to_string()
+                .map_err(|_| anyhow!("Invalid UTF-8 in FormData value"))?;
+            MultipartField::text(name, text)
+        } else if let Some(blob) = value.as_object().and_then(BlobData::from_object) {
+            MultipartField::binary(name, blob.bytes().to_vec(), filename, content_type)
+        } else if let Some(data) = value
+            .as_object()
+            .map(read_binary_data)
+            .transpose()?
+            .flatten()
+        {
+            MultipartField::binary(name, data, filename, content_type)
+        } else {
+            return Err(anyhow!("Unsupported FormData value for field '{}'", name));
+        };
+        fields.push(field);
     }
 
-    // Check if it's a typed array (Uint8Array, etc.)
-    if let Ok(Some(length)) = obj.get::<_, Option<f64>>("length") {
-        let length = length as usize;
-        // Try to extract bytes from typed array
-        for i in 0..length {
-            if let Ok(Some(byte)) = obj.get::<_, Option<f64>>(&i.to_string()) {
-                bytes.push(byte as u8);
-            } else {
-                return Err(anyhow!("Failed to extract byte at index {}", i));
-            }
-        }
-        return Ok(BodyInit::Binary(bytes));
-    }
+    Ok(fields)
+}
 
-    // If we have a buffer property (for typed arrays), try to access it
-    if let Ok(Some(buffer)) = obj.get::<_, Option<Object>>("buffer") {
-        // Recurse to handle the underlying ArrayBuffer
-        return extract_binary_data(&buffer);
+/// Serialize a JavaScript value with the context's `JSON.stringify`
+fn serialize_json(value: &Value) -> Result<String> {
+    let cx = value.ctx();
+    cx.json_stringify(value.clone())
+        .map_err(|_| {
+            // Surface the message of the exception thrown by `JSON.stringify`
+            let message = cx
+                .catch()
+                .into_object()
+                .and_then(Exception::from_object)
+                .and_then(|e| e.message())
+                .unwrap_or_default();
+            anyhow!("Failed to serialize body as JSON: {}", message)
+        })?
+        .ok_or_else(|| anyhow!("Body is not JSON serializable"))?
+        .to_string()
+        .map_err(|_| anyhow!("Invalid UTF-8 in JSON body"))
+}
+
+/// Read the bytes of an ArrayBuffer, TypedArray or DataView straight from its backing store.
+/// Returns `None` when the object is not binary data.
+fn read_binary_data(obj: &Object) -> Result<Option<Vec<u8>>> {
+    if let Some(buffer) = obj.as_array_buffer() {
+        let bytes = buffer
+            .as_bytes()
+            .ok_or_else(|| anyhow!("ArrayBuffer is detached"))?;
+        return Ok(Some(bytes.to_vec()));
     }
-    Err(anyhow!("Unable to extract binary data from object"))
+
+    // Typed arrays and DataViews are windows onto an underlying ArrayBuffer
+    let Some(buffer) = obj.get::<_, Option<Object>>("buffer")? else {
+        return Ok(None);
+    };
+    let Some(buffer) = buffer.as_array_buffer() else {
+        return Ok(None);
+    };
+    let offset = obj.get::<_, Option<f64>>("byteOffset")?.unwrap_or(0.0) as usize;
+    let length = obj
+        .get::<_, Option<f64>>("byteLength")?
+        .ok_or_else(|| anyhow!("binary view has no byteLength"))? as usize;
+    let bytes = buffer
+        .as_bytes()
+        .ok_or_else(|| anyhow!("ArrayBuffer is detached"))?
+        .get(offset..offset + length)
+        .ok_or_else(|| anyhow!("binary view is out of bounds of its ArrayBuffer"))?;
+    Ok(Some(bytes.to_vec()))
 }
 
-/// Create JavaScript Response object from Rust response
+/// Create JavaScript Response object from Rust response.
+/// The body is not part of the object, JavaScript pulls it chunk by chunk with `readChunk()`.
 fn create_js_response<'js>(
-    cx: javy_plugin_api::javy::quickjs::Ctx<'js>,
+    cx: Ctx<'js>,
     response: HttpResponse,
     url: String,
+    redirected: bool,
+    response_type: &'static str,
+    reader: BodyReader,
 ) -> Result<Value<'js>> {
     let response_obj = Object::new(cx.clone())?;
 
@@ -291,168 +807,57 @@ fn create_js_response<'js>(
         "statusText",
         Value::from_string(JSString::from_str(
             cx.clone(),
-            &get_status_text(response.status()),
+            get_status_text(response.status()),
         )?),
     )?;
     response_obj.set(
         "url",
         Value::from_string(JSString::from_str(cx.clone(), &url)?),
     )?;
-    response_obj.set("redirected", Value::new_bool(cx.clone(), false))?; // TODO: Track redirects
+    response_obj.set("redirected", Value::new_bool(cx.clone(), redirected))?;
     response_obj.set(
         "type",
-        Value::from_string(JSString::from_str(cx.clone(), "basic")?),
-    )?;
-
-    // Create headers object
-    let headers_obj = Object::new(cx.clone())?;
-    for (key, value) in response.headers() {
-        headers_obj.set(
-            key,
-            Value::from_string(JSString::from_str(cx.clone(), value)?),
-        )?;
-    }
-    response_obj.set("headers", Value::from_object(headers_obj))?;
-
-    // Store response data for cloning
-    let response_clone = response.clone();
-    let body_bytes = response.bytes().to_vec();
-    let body_consumed = std::cell::RefCell::new(false);
-
-    // Add text() method
-    let body_bytes_clone = body_bytes.clone();
-    let body_consumed_clone = body_consumed.clone();
-    response_obj.set(
-        "text",
-        Function::new(
-            cx.clone(),
-            MutFn::new(move |cx, args| {
-                let (cx, _args) = hold_and_release!(cx, args);
-
-                if *body_consumed_clone.borrow() {
-                    return Err(to_js_error(cx, anyhow!("Body already consumed")));
-                }
-                *body_consumed_clone.borrow_mut() = true;
-
-                String::from_utf8(body_bytes_clone.clone())
-                    .map_err(|_| anyhow!("Invalid UTF-8 in response body"))
-                    .and_then(|text| {
-                        JSString::from_str(cx.clone(), &text)
-                            .map(Value::from_string)
-                            .map_err(|e| anyhow!("Failed to create JS string: {:?}", e))
-                    })
-                    .map_err(|e| to_js_error(cx, e))
-            }),
-        ),
+        Value::from_string(JSString::from_str(cx.clone(), response_type)?),
     )?;
 
-    // Add json() method
-    let body_bytes_clone = body_bytes.clone();
-    let body_consumed_clone = body_consumed.clone();
+    // Headers are passed as a list of [name, value] pairs so repeated headers are kept
     response_obj.set(
-        "json",
-        Function::new(
-            cx.clone(),
-            MutFn::new(move |cx, args| {
-                let (cx, _args) = hold_and_release!(cx, args);
-
-                if *body_consumed_clone.borrow() {
-                    return Err(to_js_error(cx, anyhow!("Body already consumed")));
-                }
-                *body_consumed_clone.borrow_mut() = true;
-
-                String::from_utf8(body_bytes_clone.clone())
-                    .map_err(|_| anyhow!("Invalid UTF-8 in response body"))
-                    .and_then(|text| {
-                        let js_code = format!("({})", text);
-                        cx.eval::<Value, _>(js_code.as_bytes())
-                            .map_err(|e| anyhow!("Failed to parse JSON: {:?}", e))
-                    })
-                    .map_err(|e| to_js_error(cx, e))
-            }),
-        ),
+        "headers",
+        response_headers_to_js(cx.clone(), response.headers())?,
     )?;
 
-    // Add arrayBuffer() method
-    let body_bytes_clone = body_bytes.clone();
-    let body_consumed_clone = body_consumed.clone();
-    response_obj.set(
-        "arrayBuffer",
-        Function::new(
-            cx.clone(),
-            MutFn::new(move |cx, args| {
-                let (cx, _args) = hold_and_release!(cx, args);
-
-                if *body_consumed_clone.borrow() {
-                    return Err(to_js_error(cx, anyhow!("Body already consumed")));
-                }
-                *body_consumed_clone.borrow_mut() = true;
-
-                // Create ArrayBuffer from bytes
-                // This is a simplified version - proper implementation would create actual ArrayBuffer
-                let array_obj = Object::new(cx.clone())?;
-                array_obj.set(
-                    "byteLength",
-                    Value::new_number(cx.clone(), body_bytes_clone.len() as f64),
-                )?;
-
-                // Store bytes as array
-                for (i, byte) in body_bytes_clone.iter().enumerate() {
-                    array_obj.set(&i.to_string(), Value::new_number(cx.clone(), *byte as f64))?;
-                }
+    let reader = Rc::new(RefCell::new(reader));
 
-                Ok(Value::from_object(array_obj))
-            }),
-        ),
-    )?;
-
-    // Add blob() method (simplified - returns object with size and type)
-    let body_bytes_clone = body_bytes.clone();
-    let body_consumed_clone = body_consumed.clone();
+    // Add readChunk() method, returns the next chunk as a Uint8Array or null at the end of the body
+    let reader_clone = reader.clone();
     response_obj.set(
-        "blob",
+        "readChunk",
         Function::new(
             cx.clone(),
             MutFn::new(move |cx, args| {
                 let (cx, _args) = hold_and_release!(cx, args);
 
-                if *body_consumed_clone.borrow() {
-                    return Err(to_js_error(cx, anyhow!("Body already consumed")));
-                }
-                *body_consumed_clone.borrow_mut() = true;
-
-                let blob_obj = Object::new(cx.clone())?;
-                blob_obj.set(
-                    "size",
-                    Value::new_number(cx.clone(), body_bytes_clone.len() as f64),
-                )?;
-                blob_obj.set(
-                    "type",
-                    Value::from_string(JSString::from_str(cx.clone(), "application/octet-stream")?),
-                )?;
-
-                Ok(Value::from_object(blob_obj))
+                reader_clone
+                    .borrow_mut()
+                    .read_chunk()
+                    .and_then(|chunk| match chunk {
+                        Some(chunk) => TypedArray::<u8>::new_copy(cx.clone(), &chunk)
+                            .map(|array| array.into_value())
+                            .map_err(|e| anyhow!("Failed to create Uint8Array: {:?}", e)),
+                        None => Ok(Value::new_null(cx.clone())),
+                    })
+                    .map_err(|e| to_js_error(cx.clone(), throw_fetch_error(&cx, e)))
             }),
         ),
     )?;
 
-    // Add clone() method
-    let response_for_clone = response_clone.clone();
-    let url_for_clone = url.clone();
+    // Add cancel() method, stops reading the body
     response_obj.set(
-        "clone",
+        "cancel",
         Function::new(
             cx.clone(),
-            MutFn::new(move |cx, args| {
-                let (cx, _args) = hold_and_release!(cx, args);
-                match create_js_response(
-                    cx.clone(),
-                    response_for_clone.clone(),
-                    url_for_clone.clone(),
-                ) {
-                    Ok(cloned_response) => Ok(cloned_response),
-                    Err(e) => Err(to_js_error(cx, e)),
-                }
+            MutFn::new(move || {
+                reader.borrow_mut().cancel();
             }),
         ),
     )?;
@@ -463,6 +868,7 @@ fn create_js_response<'js>(
 /// Get status text for HTTP status code
 fn get_status_text(status: u16) -> &'static str {
     match status {
+        0 => "",
         100 => "Continue",
         101 => "Switching Protocols",
         102 => "Processing",
@@ -528,40 +934,131 @@ fn get_status_text(status: u16) -> &'static str {
     }
 }
 
+/// A request started from JavaScript that has not been sent yet
+pub struct PendingRequest(Option<(String, FetchOptions)>);
+
+impl<'js> Trace<'js> for PendingRequest {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+impl<'js> JsClass<'js> for PendingRequest {
+    const NAME: &'static str = "NativeFetchRequest";
+
+    type Mutable = Writable;
+
+    fn class_id() -> &'static ClassId {
+        static ID: ClassId = ClassId::new();
+        &ID
+    }
+
+    fn prototype(ctx: &Ctx<'js>) -> javy_plugin_api::javy::quickjs::Result<Option<Object<'js>>> {
+        Object::new(ctx.clone()).map(Some)
+    }
+
+    fn constructor(
+        _ctx: &Ctx<'js>,
+    ) -> javy_plugin_api::javy::quickjs::Result<Option<Constructor<'js>>> {
+        Ok(None)
+    }
+}
+
 /// Fetch options structure
 #[derive(Debug, Clone)]
 struct FetchOptions {
     method: String,
-    headers: HashMap<String, String>,
-    body: Option<BodyInit>,
+    headers: HeaderList,
+    /// Shared by every attempt and redirect hop, rather than copied for each
+    body: Option<Rc<BodyInit>>,
     mode: String,
-    credentials: String,
-    cache: String,
-    redirect: String,
+    credentials: Credentials,
+    cache: CacheMode,
+    redirect: RedirectMode,
+    max_redirects: u32,
     referrer: String,
     referrer_policy: String,
     integrity: String,
     keepalive: bool,
     signal: Option<AbortSignal>,
     timeout: Option<u32>,
+    retry: Option<RetryPolicy>,
+    decompress: bool,
+    /// Size of the range requests a GET body is read with, 0 sends a single request
+    chunk_size: u64,
 }
 
 impl Default for FetchOptions {
     fn default() -> Self {
         Self {
             method: "GET".to_string(),
-            headers: HashMap::new(),
+            headers: Vec::new(),
             body: None,
             mode: "cors".to_string(),
-            credentials: "same-origin".to_string(),
-            cache: "default".to_string(),
-            redirect: "follow".to_string(),
+            credentials: Credentials::SameOrigin,
+            cache: CacheMode::Default,
+            redirect: RedirectMode::Follow,
+            max_redirects: MAX_REDIRECTS,
             referrer: "about:client".to_string(),
             referrer_policy: "".to_string(),
             integrity: "".to_string(),
             keepalive: false,
             signal: None,
             timeout: None,
+            retry: None,
+            decompress: true,
+            chunk_size: 0,
+        }
+    }
+}
+
+/// How redirect responses are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RedirectMode {
+    /// Follow redirects up to the maximum hop count
+    Follow,
+    /// Reject the request when a redirect is received
+    Error,
+    /// Return an opaque-redirect response
+    Manual,
+}
+
+impl RedirectMode {
+    fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "follow" => Some(Self::Follow),
+            "error" => Some(Self::Error),
+            "manual" => Some(Self::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// Whether cookies are sent with a request and stored from its response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Credentials {
+    /// Never
+    Omit,
+    /// Only while the request stays on the origin of the requested URL
+    SameOrigin,
+    /// Always
+    Include,
+}
+
+impl Credentials {
+    fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "omit" => Some(Self::Omit),
+            "same-origin" => Some(Self::SameOrigin),
+            "include" => Some(Self::Include),
+            _ => None,
+        }
+    }
+
+    /// Whether credentials are used for `url`, reached from a request to `request_url`
+    fn allows(self, request_url: &str, url: &str) -> bool {
+        match self {
+            Self::Omit => false,
+            Self::SameOrigin => same_origin(request_url, url),
+            Self::Include => true,
         }
     }
 }
@@ -571,10 +1068,26 @@ impl Default for FetchOptions {
 enum BodyInit {
     Text(String),
     Json(String),
-    FormData(HashMap<String, String>),
+    FormData(Vec<MultipartField>),
     Binary(Vec<u8>),
 }
 
-/// Placeholder for AbortSignal support
+/// Snapshot of a JavaScript AbortSignal taken when the request is made
 #[derive(Debug, Clone)]
-struct AbortSignal {}
+struct AbortSignal {
+    aborted: bool,
+    /// Absolute deadline in milliseconds since the Unix epoch (`AbortSignal.timeout()`)
+    deadline: Option<f64>,
+}
+
+impl AbortSignal {
+    /// Milliseconds left before the signal's deadline, if it has one
+    fn remaining_ms(&self) -> Option<u32> {
+        let deadline = self.deadline?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as f64)
+            .unwrap_or(0.0);
+        Some((deadline - now).clamp(0.0, u32::MAX as f64) as u32)
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use blockless_sdk::http::HttpResponse;
+use javy_plugin_api::javy::quickjs::{Array, Exception, Object, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{
+    error::FetchError,
+    header_value,
+    headers::{has_header, parse_http_date},
+};
+
+/// Statuses retried when `retryOn` is not given: timeouts, rate limiting and transient server errors
+const DEFAULT_RETRY_ON: [u16; 6] = [408, 429, 500, 502, 503, 504];
+/// Delay before the first retry, in milliseconds
+const DEFAULT_DELAY_MS: u64 = 1000;
+/// Longest wait between attempts. Servers asking to wait longer with `Retry-After` are not retried.
+const DEFAULT_MAX_DELAY_MS: u64 = 30_000;
+
+/// How the delay grows between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backoff {
+    /// Double the delay after every attempt
+    Exponential,
+    /// Wait the same delay between attempts
+    Fixed,
+}
+
+/// The non-standard `retry` fetch option:
+/// `{ retries, backoff: "exponential" | "fixed", delay, maxDelay, retryOn: [statuses], respectRetryAfter }`.
+/// A number is a shorthand for `{ retries }`.
+///
+/// Only idempotent methods are retried, plus requests that carry an `Idempotency-Key` header.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    retries: u32,
+    backoff: Backoff,
+    delay_ms: u64,
+    max_delay_ms: u64,
+    retry_on: Vec<u16>,
+    respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff: Backoff::Exponential,
+            delay_ms: DEFAULT_DELAY_MS,
+            max_delay_ms: DEFAULT_MAX_DELAY_MS,
+            retry_on: DEFAULT_RETRY_ON.to_vec(),
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Parse the `retry` option, `None` when it is absent or asks for no retries
+    pub fn parse(value: &Value) -> Result<Option<Self>> {
+        let mut policy = Self::default();
+        if let Some(retries) = value.as_number() {
+            policy.retries = retries as u32;
+        } else if let Some(obj) = value.as_object() {
+            policy.parse_object(obj)?;
+        } else if !value.is_undefined() && !value.is_null() {
+            return Err(Exception::throw_type(
+                value.ctx(),
+                "The retry option must be a number or an object",
+            )
+            .into());
+        }
+        Ok((policy.retries > 0).then_some(policy))
+    }
+
+    fn parse_object(&mut self, obj: &Object) -> Result<()> {
+        if let Some(retries) = obj.get::<_, Option<f64>>("retries")? {
+            self.retries = retries as u32;
+        }
+        if let Some(backoff) = obj.get::<_, Option<String>>("backoff")? {
+            self.backoff = match backoff.as_str() {
+                "exponential" => Backoff::Exponential,
+                "fixed" => Backoff::Fixed,
+                _ => {
+                    return Err(Exception::throw_type(
+                        obj.ctx(),
+                        &format!(
+                            "'{}' is not a valid retry backoff, expected 'exponential' or 'fixed'",
+                            backoff
+                        ),
+                    )
+                    .into())
+                }
+            };
+        }
+        if let Some(delay) = obj.get::<_, Option<f64>>("delay")? {
+            self.delay_ms = delay.max(0.0) as u64;
+        }
+        if let Some(max_delay) = obj.get::<_, Option<f64>>("maxDelay")? {
+            self.max_delay_ms = max_delay.max(0.0) as u64;
+        }
+        if let Some(retry_on) = obj.get::<_, Option<Array>>("retryOn")? {
+            self.retry_on = retry_on
+                .iter::<f64>()
+                .map(|status| status.map(|status| status as u16))
+                .collect::<javy_plugin_api::javy::quickjs::Result<_>>()
+                .map_err(|_| {
+                    Exception::throw_type(obj.ctx(), "retryOn must be an array of status codes")
+                })?;
+        }
+        if let Some(respect_retry_after) = obj.get::<_, Option<bool>>("respectRetryAfter")? {
+            self.respect_retry_after = respect_retry_after;
+        }
+        Ok(())
+    }
+
+    /// Whether a request may be sent more than once
+    pub fn applies_to(&self, method: &str, headers: &[(String, String)]) -> bool {
+        matches!(
+            method,
+            "GET" | "HEAD" | "OPTIONS" | "PUT" | "DELETE" | "TRACE"
+        ) || has_header(headers, "idempotency-key")
+    }
+
+    /// Delay before retrying after a response, `None` if it should be returned as is
+    pub fn delay_after_response(&self, attempt: u32, response: &HttpResponse) -> Option<u64> {
+        if attempt >= self.retries || !self.retry_on.contains(&response.status) {
+            return None;
+        }
+        let retry_after = header_value(&response.headers, "retry-after")
+            .filter(|_| self.respect_retry_after)
+            .and_then(|value| parse_retry_after(&value));
+        match retry_after {
+            // The server asked to wait longer than we are willing to
+            Some(delay) if delay > self.max_delay_ms => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff_delay(attempt)),
+        }
+    }
+
+    /// Delay before retrying after a failed request, `None` if the failure is final
+    pub fn delay_after_error(&self, attempt: u32, error: &anyhow::Error) -> Option<u64> {
+        let transient = error
+            .downcast_ref::<FetchError>()
+            .is_some_and(FetchError::is_transient);
+        (attempt < self.retries && transient).then(|| self.backoff_delay(attempt))
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> u64 {
+        let delay = match self.backoff {
+            Backoff::Exponential => self.delay_ms.saturating_mul(1u64 << attempt.min(32)),
+            Backoff::Fixed => self.delay_ms,
+        };
+        delay.min(self.max_delay_ms)
+    }
+}
+
+/// Parse a `Retry-After` value, either delay seconds or an HTTP date, into milliseconds to wait
+fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(seconds.saturating_mul(1000));
+    }
+    let date = parse_http_date(value)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as i64;
+    Some(date.saturating_sub(now).max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::error::FetchErrorKind;
+    use std::collections::HashMap;
+
+    fn response(status: u16, retry_after: Option<&str>) -> HttpResponse {
+        let mut headers = HashMap::new();
+        if let Some(retry_after) = retry_after {
+            headers.insert("Retry-After".to_string(), retry_after.to_string());
+        }
+        HttpResponse {
+            status,
+            headers,
+            body: Vec::new(),
+            url: String::new(),
+        }
+    }
+
+    fn policy(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn retry_after_takes_seconds_or_a_date() {
+        assert_eq!(parse_retry_after(" 120 "), Some(120_000));
+        assert_eq!(parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"), Some(0));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-1"), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = policy(10);
+        let delays: Vec<_> = (0..7)
+            .map(|attempt| policy.backoff_delay(attempt))
+            .collect();
+        assert_eq!(
+            delays,
+            [1000, 2000, 4000, 8000, 16_000, 30_000, 30_000].to_vec()
+        );
+        assert_eq!(policy.backoff_delay(u32::MAX), DEFAULT_MAX_DELAY_MS);
+
+        let fixed = RetryPolicy {
+            backoff: Backoff::Fixed,
+            ..policy
+        };
+        assert_eq!(fixed.backoff_delay(5), DEFAULT_DELAY_MS);
+    }
+
+    #[test]
+    fn responses_are_retried_on_listed_statuses_only() {
+        let policy = policy(2);
+        assert_eq!(
+            policy.delay_after_response(0, &response(503, None)),
+            Some(1000)
+        );
+        assert_eq!(
+            policy.delay_after_response(1, &response(503, None)),
+            Some(2000)
+        );
+        assert_eq!(policy.delay_after_response(2, &response(503, None)), None);
+        assert_eq!(policy.delay_after_response(0, &response(404, None)), None);
+    }
+
+    #[test]
+    fn retry_after_is_honored_within_the_maximum_delay() {
+        let policy = policy(1);
+        assert_eq!(
+            policy.delay_after_response(0, &response(429, Some("3"))),
+            Some(3000)
+        );
+        assert_eq!(
+            policy.delay_after_response(0, &response(429, Some("60"))),
+            None
+        );
+
+        let ignoring = RetryPolicy {
+            respect_retry_after: false,
+            ..policy
+        };
+        assert_eq!(
+            ignoring.delay_after_response(0, &response(429, Some("60"))),
+            Some(1000)
+        );
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        let policy = policy(1);
+        let refused = FetchError::new(FetchErrorKind::Network, "ECONNREFUSED", "refused").into();
+        let denied = FetchError::new(FetchErrorKind::Network, "EACCES", "denied").into();
+        let other = anyhow::anyhow!("not a fetch error");
+        assert_eq!(policy.delay_after_error(0, &refused), Some(1000));
+        assert_eq!(policy.delay_after_error(1, &refused), None);
+        assert_eq!(policy.delay_after_error(0, &denied), None);
+        assert_eq!(policy.delay_after_error(0, &other), None);
+    }
+
+    #[test]
+    fn only_idempotent_requests_are_retried() {
+        let policy = policy(1);
+        assert!(policy.applies_to("PUT", &[]));
+        assert!(!policy.applies_to("POST", &[]));
+        assert!(policy.applies_to(
+            "POST",
+            &[("Idempotency-Key".to_string(), "abc".to_string())]
+        ));
+    }
+}
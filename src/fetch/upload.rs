@@ -0,0 +1,135 @@
+use anyhow::{anyhow, bail, Result};
+use javy_plugin_api::javy::{
+    quickjs::{
+        class::{ClassId, JsClass, Trace, Tracer, Writable},
+        function::Constructor,
+        Class, Ctx, Object, Value,
+    },
+    Args,
+};
+use std::{fs::File, io::Read, mem::ManuallyDrop, os::fd::FromRawFd};
+
+use super::read_binary_data;
+
+/// Largest request body collected from a stream or a file.
+/// The host takes the whole body in a single call, so it has to fit in memory at once.
+const MAX_UPLOAD_SIZE: usize = 32 * 1024 * 1024;
+
+/// A request body that is being streamed from JavaScript.
+///
+/// The host takes the whole request body in one call, so the chunks of a `ReadableStream`
+/// or async iterable are appended here as they are produced, up to `MAX_UPLOAD_SIZE`, and
+/// sent as a single request. Each chunk is copied out of QuickJS memory right away, so the
+/// script never holds more than one chunk at a time.
+pub struct UploadData(Vec<u8>);
+
+impl UploadData {
+    /// Take the collected bytes behind a native handle, if the object is one
+    pub fn take_from_object(obj: &Object<'_>) -> Option<Vec<u8>> {
+        Class::<UploadData>::from_object(obj).map(|class| std::mem::take(&mut class.borrow_mut().0))
+    }
+}
+
+impl<'js> Trace<'js> for UploadData {
+    fn trace<'a>(&self, _tracer: Tracer<'a, 'js>) {}
+}
+
+impl<'js> JsClass<'js> for UploadData {
+    const NAME: &'static str = "NativeUpload";
+
+    type Mutable = Writable;
+
+    fn class_id() -> &'static ClassId {
+        static ID: ClassId = ClassId::new();
+        &ID
+    }
+
+    fn prototype(ctx: &Ctx<'js>) -> javy_plugin_api::javy::quickjs::Result<Option<Object<'js>>> {
+        Object::new(ctx.clone()).map(Some)
+    }
+
+    fn constructor(
+        _ctx: &Ctx<'js>,
+    ) -> javy_plugin_api::javy::quickjs::Result<Option<Constructor<'js>>> {
+        Ok(None)
+    }
+}
+
+/// Create an empty upload body.
+pub fn bless_upload_create(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, _args) = args.release();
+    Ok(Class::instance(cx, UploadData(Vec::new()))?.into_value())
+}
+
+/// Append a chunk to an upload body.
+/// Chunks can be strings (encoded as UTF-8), ArrayBuffers, typed arrays or DataViews.
+pub fn bless_upload_write(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let [handle, chunk, ..] = args.as_slice() else {
+        bail!(
+            "upload_write expects 2 parameters: the upload handle and a chunk, Got: {} parameters.",
+            args.len()
+        );
+    };
+    let upload = handle
+        .as_object()
+        .and_then(Class::<UploadData>::from_object)
+        .ok_or_else(|| anyhow!("upload_write: invalid upload handle"))?;
+
+    let data = if let Some(s) = chunk.as_string() {
+        s.to_string()
+            .map_err(|_| anyhow!("invalid UTF-8 in body chunk"))?
+            .into_bytes()
+    } else if let Some(data) = chunk
+        .as_object()
+        .map(read_binary_data)
+        .transpose()?
+        .flatten()
+    {
+        data
+    } else {
+        bail!("body stream chunks must be strings, ArrayBuffers or ArrayBuffer views");
+    };
+
+    let mut upload = upload.borrow_mut();
+    if upload.0.len() + data.len() > MAX_UPLOAD_SIZE {
+        bail!(too_large());
+    }
+    upload.0.extend_from_slice(&data);
+    Ok(Value::new_undefined(cx))
+}
+
+/// Create an upload body with the rest of an open file, read from its current position.
+/// The file is read in Rust so its contents never pass through QuickJS, but it is still read
+/// whole before the request is sent, so it must not be larger than `MAX_UPLOAD_SIZE`.
+pub fn bless_upload_from_fd(args: Args<'_>) -> Result<Value<'_>> {
+    let (cx, args) = args.release();
+    let fd = args
+        .first()
+        .and_then(Value::as_int)
+        .ok_or_else(|| anyhow!("upload_from_fd expects a file descriptor"))?;
+
+    // SAFETY: `File` only borrows the descriptor here, it is never closed because the
+    // `ManuallyDrop` wrapper is not dropped, so the script keeps owning it. Any integer is
+    // sound to wrap: on WASI a descriptor is an index into the host's table, and reading one
+    // that is not open, or not a file, fails with an error instead of touching other memory,
+    // and reading one the script did not open only moves that descriptor's position.
+    let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    let mut bytes = Vec::new();
+    file.by_ref()
+        .take(MAX_UPLOAD_SIZE as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| anyhow!("Failed to read file descriptor {}: {}", fd, e))?;
+    if bytes.len() > MAX_UPLOAD_SIZE {
+        bail!(too_large());
+    }
+
+    Ok(Class::instance(cx, UploadData(bytes))?.into_value())
+}
+
+fn too_large() -> String {
+    format!(
+        "request body is larger than the {} MiB limit for streamed and file bodies",
+        MAX_UPLOAD_SIZE / (1024 * 1024)
+    )
+}
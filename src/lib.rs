@@ -9,12 +9,18 @@ use javy_plugin_api::{
     Config,
 };
 
+#[cfg(any(feature = "compression", feature = "fetch"))]
+mod bounded;
+#[cfg(feature = "compression")]
+pub mod compression;
 #[cfg(feature = "crypto")]
 pub mod crypto;
 #[cfg(feature = "fetch")]
 pub mod fetch;
 #[cfg(feature = "llm")]
 pub mod llm;
+#[cfg(feature = "url")]
+pub mod url;
 #[cfg(feature = "wasip1")]
 pub mod wasi;
 
@@ -36,8 +42,14 @@ pub extern "C" fn initialize_runtime() {
             .with(|ctx| {
                 #[cfg(feature = "crypto")]
                 set_crypto_globals(&ctx)?;
+                #[cfg(feature = "url")]
+                set_url_globals(&ctx)?;
+                #[cfg(any(feature = "compression", feature = "fetch"))]
+                set_stream_globals(&ctx)?;
                 #[cfg(feature = "fetch")]
                 set_fetch_globals(&ctx)?;
+                #[cfg(feature = "compression")]
+                set_compression_globals(&ctx)?;
                 #[cfg(feature = "wasip1")]
                 set_wasi_globals(&ctx)?;
                 #[cfg(feature = "llm")]
@@ -68,22 +80,123 @@ pub fn set_crypto_globals(ctx: &Ctx<'_>) -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "url")]
+pub fn set_url_globals(ctx: &Ctx<'_>) -> Result<()> {
+    macro_rules! bind {
+        (function, $l: ident) => {
+            let name = concat!("__javy_", stringify!($l));
+            ctx.globals().set(
+                name,
+                Function::new(
+                    ctx.clone(),
+                    MutFn::new(move |cx, args| {
+                        let (cx, args) = hold_and_release!(cx, args);
+                        url::$l(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+                    }),
+                )?,
+            )?;
+        };
+    }
+    bind!(function, bless_url_parse);
+    bind!(function, bless_url_get);
+    bind!(function, bless_url_set);
+    bind!(function, bless_url_search_params_parse);
+    bind!(function, bless_url_search_params_serialize);
+    ctx.eval::<(), _>(include_str!("url/url.js"))?;
+    Ok(())
+}
+
+#[cfg(any(feature = "compression", feature = "fetch"))]
+pub fn set_stream_globals(ctx: &Ctx<'_>) -> Result<()> {
+    ctx.eval::<(), _>(include_str!("streams/streams.js"))?;
+    Ok(())
+}
+
 #[cfg(feature = "fetch")]
 pub fn set_fetch_globals(ctx: &Ctx<'_>) -> Result<()> {
     ctx.globals().set(
-        "__javy_fetchio_request",
+        "__javy_fetchio_start",
         Function::new(
             ctx.clone(),
             MutFn::new(move |cx, args| {
                 let (cx, args) = hold_and_release!(cx, args);
-                fetch::bless_fetch_request(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+                fetch::bless_fetch_start(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
             }),
         )?,
     )?;
+    ctx.globals().set(
+        "__javy_fetchio_complete",
+        Function::new(
+            ctx.clone(),
+            MutFn::new(move |cx, args| {
+                let (cx, args) = hold_and_release!(cx, args);
+                fetch::bless_fetch_complete(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+            }),
+        )?,
+    )?;
+
+    macro_rules! bind {
+        (function, $l: ident) => {
+            let name = concat!("__javy_", stringify!($l));
+            ctx.globals().set(
+                name,
+                Function::new(
+                    ctx.clone(),
+                    MutFn::new(move |cx, args| {
+                        let (cx, args) = hold_and_release!(cx, args);
+                        fetch::$l(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+                    }),
+                )?,
+            )?;
+        };
+    }
+    bind!(function, bless_blob_create);
+    bind!(function, bless_blob_slice);
+    bind!(function, bless_blob_size);
+    bind!(function, bless_blob_read);
+    bind!(function, bless_blob_text);
+    bind!(function, bless_form_data_parse);
+    bind!(function, bless_upload_create);
+    bind!(function, bless_upload_write);
+    bind!(function, bless_upload_from_fd);
+    bind!(function, bless_event_stream_create);
+    bind!(function, bless_event_stream_parse);
+    bind!(function, bless_event_stream_retry);
+    bind!(function, bless_fetch_cache_persist);
+    bind!(function, bless_fetch_cache_clear);
+    bind!(function, bless_cookie_jar_enable);
+    bind!(function, bless_cookie_jar_list);
+    bind!(function, bless_cookie_jar_clear);
     ctx.eval::<(), _>(include_str!("fetch/fetch.js"))?;
     Ok(())
 }
 
+#[cfg(feature = "compression")]
+pub fn set_compression_globals(ctx: &Ctx<'_>) -> Result<()> {
+    macro_rules! bind {
+        (function, $l: ident) => {
+            let name = concat!("__javy_", stringify!($l));
+            ctx.globals().set(
+                name,
+                Function::new(
+                    ctx.clone(),
+                    MutFn::new(move |cx, args| {
+                        let (cx, args) = hold_and_release!(cx, args);
+                        compression::$l(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+                    }),
+                )?,
+            )?;
+        };
+    }
+    bind!(function, bless_compression_create);
+    bind!(function, bless_compression_write);
+    bind!(function, bless_compression_finish);
+    bind!(function, bless_compress);
+    bind!(function, bless_decompress);
+    ctx.eval::<(), _>(include_str!("compression/compression.js"))?;
+    Ok(())
+}
+
 #[cfg(feature = "wasip1")]
 pub fn set_wasi_globals(ctx: &Ctx<'_>) -> Result<()> {
     macro_rules! bind {
@@ -136,5 +249,6 @@ pub fn set_llm_globals(ctx: &Ctx<'_>) -> Result<()> {
             llm::supported_models_object(ctx).map_err(|e| to_js_error(ctx_clone, e))?,
         ),
     )?;
+    ctx.eval::<(), _>(include_str!("llm/llm.js"))?;
     Ok(())
 }
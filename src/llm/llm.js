@@ -0,0 +1,38 @@
+// Wrap everything in an anonymous function to avoid leaking local variables into the global scope.
+(function () {
+    // Get a reference to the native constructor before we replace it on `globalThis`.
+    const createLLM = globalThis.BlessLLM;
+
+    function BlessLLM(model) {
+        const llm = createLLM(model);
+        const chat = llm.chat;
+        const chatMessages = llm.chatMessages;
+
+        // The host call behind a chat is blocking: a request started by chatAsync runs once the
+        // current script turn ends, and the whole module waits for the model's reply. Requests
+        // never overlap each other or other work such as fetch, they run one at a time in the
+        // order they were made, as they share the instance's conversation.
+        let pending = Promise.resolve();
+        const schedule = (request) => {
+            const result = pending.then(request);
+            pending = result.catch(() => {});
+            return result;
+        };
+
+        // Resolves with the reply to a prompt, or with the assistant message
+        // when given an array of messages as `chatMessages` takes. This is `chat` and
+        // `chatMessages` behind a promise, it does not make the request run in the background.
+        llm.chatAsync = (input) => {
+            if (typeof input === 'string') {
+                return schedule(() => chat(input));
+            }
+            if (Array.isArray(input)) {
+                return schedule(() => chatMessages(input));
+            }
+            return Promise.reject(new TypeError('chatAsync expects a prompt or an array of messages'));
+        };
+        return llm;
+    }
+
+    globalThis.BlessLLM = BlessLLM;
+})();
@@ -1,8 +1,8 @@
 use anyhow::{anyhow, Result};
-use blockless_sdk::llm::{BlocklessLlm, LlmOptions, Models};
+use blockless_sdk::llm::Models;
 use javy_plugin_api::javy::{
     hold, hold_and_release,
-    quickjs::{prelude::MutFn, Function, Object, String as JSString, Value},
+    quickjs::{prelude::MutFn, Array, Ctx, Exception, Function, Object, String as JSString, Value},
     to_js_error, Args,
 };
 use std::{
@@ -10,6 +10,13 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+mod options;
+mod schema;
+mod session;
+
+use options::ChatOptions;
+use session::{ChatMessage, ChatSession, Role};
+
 pub fn supported_models_object<'js>(
     cx: &javy_plugin_api::javy::quickjs::Ctx<'js>,
 ) -> Result<Object<'js>> {
@@ -75,8 +82,8 @@ pub fn bless_llm_plugin(args: Args<'_>) -> Result<Value<'_>> {
     let model =
         Models::from_str(&model_name).map_err(|_| anyhow!("Invalid model name: {}", model_name))?;
 
-    // Create BlocklessLlm instance using SDK
-    let llm = Arc::new(Mutex::new(BlocklessLlm::new(model).unwrap()));
+    // Create the chat session, which holds the BlocklessLlm instance from the SDK
+    let llm = Arc::new(Mutex::new(ChatSession::new(model)?));
 
     // Convert to QuickJS object and expose SDK methods
     let instance = Object::new(cx.clone())?;
@@ -93,7 +100,7 @@ pub fn bless_llm_plugin(args: Args<'_>) -> Result<Value<'_>> {
                 let (cx, args) = hold_and_release!(cx, args);
 
                 let set_options = |args: Args<'_>| {
-                    let (_cx, args) = args.release();
+                    let (args_cx, args) = args.release();
 
                     if args.len() != 1 {
                         return Err(anyhow!("options must be an object"));
@@ -103,24 +110,14 @@ pub fn bless_llm_plugin(args: Args<'_>) -> Result<Value<'_>> {
                         .as_object()
                         .ok_or_else(|| anyhow!("options must be an object"))?;
 
-                    let system_message = opts_obj.get::<_, Option<String>>("system_message")?;
-                    let tools_sse_urls =
-                        opts_obj.get::<_, Option<Vec<String>>>("tools_sse_urls")?;
-                    let temperature = opts_obj.get::<_, Option<f64>>("temperature")?;
-                    let top_p = opts_obj.get::<_, Option<f64>>("top_p")?;
-                    let options = LlmOptions {
-                        system_message,
-                        tools_sse_urls,
-                        temperature: temperature.map(|t| t as f32),
-                        top_p: top_p.map(|t| t as f32),
-                    };
+                    // Values out of range are rejected here rather than passed to the host
+                    let options = ChatOptions::from_js(&args_cx, opts_obj)?;
 
                     llm_ref
                         .lock()
                         .unwrap()
                         .set_options(options)
                         .map(|_| Value::new_undefined(cx.clone()))
-                        .map_err(|e| anyhow!("Set options failed: {:?}", e))
                 };
 
                 set_options(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
@@ -139,7 +136,10 @@ pub fn bless_llm_plugin(args: Args<'_>) -> Result<Value<'_>> {
                 let get_options = |args: Args<'_>| {
                     let (_cx, _args) = args.release();
 
-                    let options = llm_ref.lock().unwrap().get_options().unwrap();
+                    let ChatOptions {
+                        base: options,
+                        sampling,
+                    } = llm_ref.lock().unwrap().options()?;
 
                     let opts_obj = Object::new(cx.clone())?;
 
@@ -169,15 +169,21 @@ pub fn bless_llm_plugin(args: Args<'_>) -> Result<Value<'_>> {
                         opts_obj.set("tools_sse_urls", Value::from_object(urls_array))?;
                     }
 
+                    // The host reports these as f32, go through their shortest
+                    // representation so 0.7 reads back as 0.7
                     if let Some(temperature) = options.temperature {
                         opts_obj.set(
                             "temperature",
-                            Value::new_number(cx.clone(), temperature.into()),
+                            Value::new_number(cx.clone(), temperature.to_string().parse()?),
                         )?;
                     }
                     if let Some(top_p) = options.top_p {
-                        opts_obj.set("top_p", Value::new_number(cx.clone(), top_p.into()))?;
+                        opts_obj.set(
+                            "top_p",
+                            Value::new_number(cx.clone(), top_p.to_string().parse()?),
+                        )?;
                     }
+                    sampling.to_js(&cx, &opts_obj)?;
 
                     Ok(Value::from_object(opts_obj))
                 };
@@ -207,21 +213,228 @@ pub fn bless_llm_plugin(args: Args<'_>) -> Result<Value<'_>> {
                         .ok_or_else(|| anyhow!("prompt required"))?
                         .to_string()
                         .map_err(|_| anyhow!("invalid UTF-8 in prompt"))?;
-                    llm_ref
+                    llm_ref.lock().unwrap().chat(&prompt).map(|res| {
+                        let js_string = JSString::from_str(cx.clone(), &res).unwrap();
+                        Value::from_string(js_string)
+                    })
+                };
+
+                chat(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+            }),
+        ),
+    )?;
+
+    let llm_ref = Arc::clone(&llm);
+    instance.set(
+        "chatMessages",
+        Function::new(
+            cx.clone(),
+            MutFn::new(move |cx, args| {
+                let (cx, args) = hold_and_release!(cx, args);
+
+                let chat_messages = |args: Args<'_>| {
+                    let (_cx, args) = args.release();
+
+                    if args.len() != 1 {
+                        return Err(anyhow!("messages required"));
+                    }
+
+                    let messages = messages_from_js(&args[0])?;
+                    let reply = llm_ref.lock().unwrap().chat_messages(messages)?;
+                    message_to_js(&cx, &reply)
+                };
+
+                chat_messages(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+            }),
+        ),
+    )?;
+
+    let llm_ref = Arc::clone(&llm);
+    instance.set(
+        "history",
+        Function::new(
+            cx.clone(),
+            MutFn::new(move |cx, args| {
+                let (cx, args) = hold_and_release!(cx, args);
+
+                let history = |args: Args<'_>| {
+                    let (_cx, _args) = args.release();
+
+                    let messages = Array::new(cx.clone())?;
+                    for (i, message) in llm_ref.lock().unwrap().history().iter().enumerate() {
+                        messages.set(i, message_to_js(&cx, message)?)?;
+                    }
+                    Ok(messages.into_value())
+                };
+
+                history(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+            }),
+        ),
+    )?;
+
+    let llm_ref = Arc::clone(&llm);
+    instance.set(
+        "reset",
+        Function::new(
+            cx.clone(),
+            MutFn::new(move |cx, args| {
+                let (cx, args) = hold_and_release!(cx, args);
+
+                let reset = |args: Args<'_>| {
+                    let (_cx, _args) = args.release();
+
+                    llm_ref.lock().unwrap().reset()?;
+                    Ok(Value::new_undefined(cx.clone()))
+                };
+
+                reset(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+            }),
+        ),
+    )?;
+
+    let llm_ref = Arc::clone(&llm);
+    instance.set(
+        "chatJSON",
+        Function::new(
+            cx.clone(),
+            MutFn::new(move |cx, args| {
+                let (cx, args) = hold_and_release!(cx, args);
+
+                let chat_json = |args: Args<'_>| {
+                    let (args_cx, args) = args.release();
+
+                    if !(2..=3).contains(&args.len()) {
+                        return Err(anyhow!("prompt and schema required"));
+                    }
+
+                    let prompt = args[0]
+                        .as_string()
+                        .ok_or_else(|| anyhow!("prompt required"))?
+                        .to_string()
+                        .map_err(|_| anyhow!("invalid UTF-8 in prompt"))?;
+                    let schema = json_schema_from_js(&args_cx, &args[1])?;
+                    let retries = json_retries_from_js(&args_cx, args.get(2))?;
+
+                    let value = llm_ref
                         .lock()
                         .unwrap()
-                        .chat_request(&prompt)
-                        .map(|res| {
-                            let js_string = JSString::from_str(cx.clone(), &res).unwrap();
-                            Value::from_string(js_string)
-                        })
-                        .map_err(|e| anyhow!("Chat request failed: {:?}", e))
+                        .chat_json(&prompt, &schema, retries)?;
+                    Ok(cx.json_parse(serde_json::to_string(&value)?)?)
                 };
 
-                chat(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
+                chat_json(hold!(cx.clone(), args)).map_err(|e| to_js_error(cx, e))
             }),
         ),
     )?;
 
     Ok(Value::from_object(instance))
 }
+
+/// Repair prompts sent by `chatJSON` unless the `retries` option says otherwise
+const DEFAULT_JSON_RETRIES: u32 = 2;
+
+/// Most repair prompts `chatJSON` accepts to send
+const MAX_JSON_RETRIES: u32 = 10;
+
+/// Read the JSON Schema passed to `chatJSON`
+fn json_schema_from_js<'js>(cx: &Ctx<'js>, value: &Value<'js>) -> Result<serde_json::Value> {
+    let invalid = || -> anyhow::Error {
+        Exception::throw_type(cx, "schema must be a JSON Schema object").into()
+    };
+    if !value.is_object() && !value.is_bool() {
+        return Err(invalid());
+    }
+    let schema = cx
+        .json_stringify(value.clone())?
+        .ok_or_else(invalid)?
+        .to_string()?;
+    let schema = serde_json::from_str(&schema)?;
+    schema::check_supported(&schema).map_err(|e| {
+        Exception::throw_type(cx, &format!("schema is not supported by chatJSON: {}", e))
+    })?;
+    Ok(schema)
+}
+
+/// Read the `{ retries }` options passed to `chatJSON`
+fn json_retries_from_js<'js>(cx: &Ctx<'js>, value: Option<&Value<'js>>) -> Result<u32> {
+    let Some(options) = value.filter(|value| !value.is_undefined()) else {
+        return Ok(DEFAULT_JSON_RETRIES);
+    };
+    let options = options
+        .as_object()
+        .ok_or_else(|| Exception::throw_type(cx, "chatJSON options must be an object"))?;
+    let retries = options.get::<_, Value>("retries")?;
+    if retries.is_undefined() {
+        return Ok(DEFAULT_JSON_RETRIES);
+    }
+    retries
+        .as_number()
+        .filter(|n| n.fract() == 0.0 && (0.0..=f64::from(MAX_JSON_RETRIES)).contains(n))
+        .map(|n| n as u32)
+        .ok_or_else(|| {
+            Exception::throw_range(
+                cx,
+                &format!(
+                    "retries must be an integer between 0 and {}",
+                    MAX_JSON_RETRIES
+                ),
+            )
+            .into()
+        })
+}
+
+/// Read the `[{ role, content }, ...]` messages passed to `chatMessages`
+fn messages_from_js(value: &Value<'_>) -> Result<Vec<ChatMessage>> {
+    let messages = value
+        .as_array()
+        .ok_or_else(|| anyhow!("messages must be an array of {{ role, content }} objects"))?;
+
+    messages
+        .iter::<Value>()
+        .enumerate()
+        .map(|(i, message)| {
+            let message = message?;
+            let message = message
+                .as_object()
+                .ok_or_else(|| anyhow!("messages[{}] must be a {{ role, content }} object", i))?;
+
+            let role = message.get::<_, Option<String>>("role").ok().flatten();
+            let role = role.as_deref().and_then(Role::parse).ok_or_else(|| {
+                anyhow!(
+                    "messages[{}].role must be 'system', 'user', 'assistant' or 'tool'",
+                    i
+                )
+            })?;
+            let content = message
+                .get::<_, Option<String>>("content")
+                .ok()
+                .flatten()
+                .ok_or_else(|| anyhow!("messages[{}].content must be a string", i))?;
+            let optional = |key: &str| {
+                message
+                    .get::<_, Option<String>>(key)
+                    .map_err(|_| anyhow!("messages[{}].{} must be a string", i, key))
+            };
+
+            Ok(ChatMessage {
+                role,
+                content,
+                name: optional("name")?,
+                tool_call_id: optional("tool_call_id")?,
+            })
+        })
+        .collect()
+}
+
+fn message_to_js<'js>(cx: &Ctx<'js>, message: &ChatMessage) -> Result<Value<'js>> {
+    let message_obj = Object::new(cx.clone())?;
+    message_obj.set("role", message.role.as_str())?;
+    message_obj.set("content", message.content.as_str())?;
+    if let Some(name) = &message.name {
+        message_obj.set("name", name.as_str())?;
+    }
+    if let Some(tool_call_id) = &message.tool_call_id {
+        message_obj.set("tool_call_id", tool_call_id.as_str())?;
+    }
+    Ok(Value::from_object(message_obj))
+}
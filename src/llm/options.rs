@@ -0,0 +1,417 @@
+use anyhow::Result;
+use blockless_sdk::llm::LlmOptions;
+use javy_plugin_api::javy::quickjs::{Array, Ctx, Exception, Object, Value};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Largest integer a JavaScript number holds exactly, the upper bound for seeds
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Most tokens a completion may ask for. Completions are read into a buffer of `u16::MAX`
+/// bytes and every token takes at least one byte, so longer ones could never be read.
+const MAX_COMPLETION_TOKENS: f64 = u16::MAX as f64;
+
+/// Options set with `setOptions`: those the SDK knows, and the sampling parameters it does
+/// not, which are sent to the host along with them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatOptions {
+    pub base: LlmOptions,
+    pub sampling: SamplingOptions,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SamplingOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f64>,
+    /// `{ type: "text" | "json_object" }` or `{ type: "json_schema", json_schema: { name, schema } }`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+}
+
+impl ChatOptions {
+    /// Read and validate the options object passed to `setOptions`
+    pub fn from_js<'js>(cx: &Ctx<'js>, opts_obj: &Object<'js>) -> Result<Self> {
+        let system_message = opts_obj.get::<_, Option<String>>("system_message")?;
+        let tools_sse_urls = opts_obj.get::<_, Option<Vec<String>>>("tools_sse_urls")?;
+        let temperature = ranged(cx, opts_obj, "temperature", 0.0..=2.0, "between 0 and 2")?;
+        let top_p = ranged(cx, opts_obj, "top_p", 0.0..=1.0, "between 0 and 1")?;
+
+        let sampling = SamplingOptions {
+            max_tokens: integer(cx, opts_obj, "max_tokens", 1.0, MAX_COMPLETION_TOKENS)?
+                .map(|n| n as u32),
+            top_k: integer(cx, opts_obj, "top_k", 1.0, u32::MAX.into())?.map(|n| n as u32),
+            stop: stop_sequences(cx, opts_obj)?,
+            seed: integer(cx, opts_obj, "seed", 0.0, MAX_SAFE_INTEGER)?.map(|n| n as u64),
+            frequency_penalty: ranged(
+                cx,
+                opts_obj,
+                "frequency_penalty",
+                -2.0..=2.0,
+                "between -2 and 2",
+            )?,
+            presence_penalty: ranged(
+                cx,
+                opts_obj,
+                "presence_penalty",
+                -2.0..=2.0,
+                "between -2 and 2",
+            )?,
+            repetition_penalty: ranged(
+                cx,
+                opts_obj,
+                "repetition_penalty",
+                f64::MIN_POSITIVE..=f64::MAX,
+                "greater than 0",
+            )?,
+            response_format: response_format(cx, opts_obj)?,
+        };
+
+        Ok(Self {
+            base: LlmOptions {
+                system_message,
+                tools_sse_urls,
+                temperature: temperature.map(|t| t as f32),
+                top_p: top_p.map(|t| t as f32),
+            },
+            sampling,
+        })
+    }
+
+    /// The options as JSON for the host, leaving out those that are not set
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        let options = HostOptions {
+            system_message: self.base.system_message.as_ref(),
+            tools_sse_urls: self.base.tools_sse_urls.as_ref(),
+            temperature: self.base.temperature,
+            top_p: self.base.top_p,
+            sampling: &self.sampling,
+        };
+        Ok(serde_json::to_vec(&options)?)
+    }
+}
+
+/// The JSON sent to the host, the options the SDK knows followed by the sampling parameters
+#[derive(Serialize)]
+struct HostOptions<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_message: Option<&'a String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools_sse_urls: Option<&'a Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(flatten)]
+    sampling: &'a SamplingOptions,
+}
+
+impl SamplingOptions {
+    /// Read the sampling parameters from the options the host reports for a handle, leaving
+    /// out those it does not report or reports in another shape
+    pub fn from_host(options: &serde_json::Value) -> Self {
+        fn field<T: DeserializeOwned>(options: &serde_json::Value, key: &str) -> Option<T> {
+            options
+                .get(key)
+                .filter(|value| !value.is_null())
+                .and_then(|value| serde_json::from_value(value.clone()).ok())
+        }
+        Self {
+            max_tokens: field(options, "max_tokens"),
+            top_k: field(options, "top_k"),
+            stop: field(options, "stop"),
+            seed: field(options, "seed"),
+            frequency_penalty: field(options, "frequency_penalty"),
+            presence_penalty: field(options, "presence_penalty"),
+            repetition_penalty: field(options, "repetition_penalty"),
+            response_format: field(options, "response_format"),
+        }
+    }
+
+    /// Names of the sampling parameters set here that the host reports with another value,
+    /// or does not report at all
+    pub fn not_applied(&self, reported: &SamplingOptions) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field.is_some() && self.$field != reported.$field {
+                        names.push(stringify!($field));
+                    }
+                )*
+            };
+        }
+        compare!(
+            max_tokens,
+            top_k,
+            stop,
+            seed,
+            frequency_penalty,
+            presence_penalty,
+            repetition_penalty,
+            response_format
+        );
+        names
+    }
+
+    /// Add the sampling parameters that are set to the object returned by `getOptions`
+    pub fn to_js<'js>(&self, cx: &Ctx<'js>, opts_obj: &Object<'js>) -> Result<()> {
+        if let Some(max_tokens) = self.max_tokens {
+            opts_obj.set("max_tokens", max_tokens)?;
+        }
+        if let Some(top_k) = self.top_k {
+            opts_obj.set("top_k", top_k)?;
+        }
+        if let Some(stop) = &self.stop {
+            let stop_array = Array::new(cx.clone())?;
+            for (i, sequence) in stop.iter().enumerate() {
+                stop_array.set(i, sequence.as_str())?;
+            }
+            opts_obj.set("stop", stop_array)?;
+        }
+        if let Some(seed) = self.seed {
+            opts_obj.set("seed", seed as f64)?;
+        }
+        if let Some(frequency_penalty) = self.frequency_penalty {
+            opts_obj.set("frequency_penalty", frequency_penalty)?;
+        }
+        if let Some(presence_penalty) = self.presence_penalty {
+            opts_obj.set("presence_penalty", presence_penalty)?;
+        }
+        if let Some(repetition_penalty) = self.repetition_penalty {
+            opts_obj.set("repetition_penalty", repetition_penalty)?;
+        }
+        if let Some(response_format) = &self.response_format {
+            opts_obj.set(
+                "response_format",
+                cx.json_parse(serde_json::to_string(response_format)?)?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn type_error(cx: &Ctx<'_>, message: String) -> anyhow::Error {
+    Exception::throw_type(cx, &message).into()
+}
+
+fn range_error(cx: &Ctx<'_>, message: String) -> anyhow::Error {
+    Exception::throw_range(cx, &message).into()
+}
+
+/// A number option, `None` when it is not set
+fn number(cx: &Ctx<'_>, opts_obj: &Object<'_>, key: &str) -> Result<Option<f64>> {
+    let value = opts_obj.get::<_, Value>(key)?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    value
+        .as_number()
+        .filter(|n| !n.is_nan())
+        .map(Some)
+        .ok_or_else(|| type_error(cx, format!("{} must be a number", key)))
+}
+
+fn ranged(
+    cx: &Ctx<'_>,
+    opts_obj: &Object<'_>,
+    key: &str,
+    range: std::ops::RangeInclusive<f64>,
+    expected: &str,
+) -> Result<Option<f64>> {
+    match number(cx, opts_obj, key)? {
+        Some(n) if !range.contains(&n) => Err(range_error(
+            cx,
+            format!("{} must be {}, got {}", key, expected, n),
+        )),
+        n => Ok(n),
+    }
+}
+
+fn integer(
+    cx: &Ctx<'_>,
+    opts_obj: &Object<'_>,
+    key: &str,
+    min: f64,
+    max: f64,
+) -> Result<Option<f64>> {
+    match number(cx, opts_obj, key)? {
+        Some(n) if n.fract() != 0.0 || n < min || n > max => Err(range_error(
+            cx,
+            format!(
+                "{} must be an integer between {} and {}, got {}",
+                key, min, max, n
+            ),
+        )),
+        n => Ok(n),
+    }
+}
+
+/// `stop` is a sequence or an array of non-empty sequences
+fn stop_sequences<'js>(cx: &Ctx<'js>, opts_obj: &Object<'js>) -> Result<Option<Vec<String>>> {
+    let value = opts_obj.get::<_, Value>("stop")?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    let invalid = || {
+        type_error(
+            cx,
+            "stop must be a string or an array of strings".to_string(),
+        )
+    };
+    let stop = if let Some(sequence) = value.as_string() {
+        vec![sequence.to_string()?]
+    } else if let Some(sequences) = value.as_array() {
+        sequences
+            .iter::<Value>()
+            .map(|sequence| {
+                sequence?
+                    .as_string()
+                    .ok_or_else(invalid)?
+                    .to_string()
+                    .map_err(Into::into)
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        return Err(invalid());
+    };
+
+    if stop.iter().any(String::is_empty) {
+        return Err(range_error(
+            cx,
+            "stop sequences must not be empty".to_string(),
+        ));
+    }
+    // An empty list is the same as no stop sequences
+    Ok((!stop.is_empty()).then_some(stop))
+}
+
+/// `response_format` as OpenAI compatible backends take it
+fn response_format<'js>(
+    cx: &Ctx<'js>,
+    opts_obj: &Object<'js>,
+) -> Result<Option<serde_json::Value>> {
+    let value = opts_obj.get::<_, Value>("response_format")?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    let invalid = |message: &str| type_error(cx, format!("response_format {}", message));
+
+    let format = value
+        .as_object()
+        .ok_or_else(|| invalid("must be an object such as { type: 'json_object' }"))?;
+    match format
+        .get::<_, Option<String>>("type")
+        .ok()
+        .flatten()
+        .as_deref()
+    {
+        Some("text" | "json_object") => {}
+        Some("json_schema") => {
+            let json_schema = format
+                .get::<_, Option<Object>>("json_schema")
+                .ok()
+                .flatten()
+                .ok_or_else(|| invalid("of type 'json_schema' requires a json_schema object"))?;
+            if json_schema
+                .get::<_, Option<Object>>("schema")
+                .ok()
+                .flatten()
+                .is_none()
+            {
+                return Err(invalid("json_schema.schema must be a JSON Schema object"));
+            }
+        }
+        _ => {
+            return Err(invalid(
+                "type must be 'text', 'json_object' or 'json_schema'",
+            ))
+        }
+    }
+
+    let json = cx
+        .json_stringify(value)?
+        .ok_or_else(|| invalid("must be serializable as JSON"))?
+        .to_string()?;
+    Ok(Some(serde_json::from_str(&json)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sampling_parameters_are_read_from_the_host_options() {
+        let reported = json!({
+            "system_message": "Be brief.",
+            "max_tokens": 256,
+            "stop": ["END"],
+            "seed": null,
+            "top_k": "forty",
+            "repetition_penalty": 1.1,
+        });
+        let sampling = SamplingOptions::from_host(&reported);
+        assert_eq!(
+            sampling,
+            SamplingOptions {
+                max_tokens: Some(256),
+                stop: Some(vec!["END".to_string()]),
+                repetition_penalty: Some(1.1),
+                ..SamplingOptions::default()
+            }
+        );
+        assert_eq!(
+            SamplingOptions::from_host(&json!({})),
+            SamplingOptions::default()
+        );
+    }
+
+    #[test]
+    fn sampling_parameters_the_host_dropped_or_changed_are_listed() {
+        let set = SamplingOptions {
+            max_tokens: Some(256),
+            seed: Some(7),
+            stop: Some(vec!["END".to_string()]),
+            ..SamplingOptions::default()
+        };
+        assert!(set.not_applied(&set).is_empty());
+        let reported = SamplingOptions {
+            max_tokens: Some(128),
+            stop: Some(vec!["END".to_string()]),
+            top_k: Some(40),
+            ..SamplingOptions::default()
+        };
+        assert_eq!(set.not_applied(&reported), vec!["max_tokens", "seed"]);
+        assert!(SamplingOptions::default().not_applied(&reported).is_empty());
+    }
+
+    #[test]
+    fn options_are_sent_as_one_json_object() {
+        let options = ChatOptions {
+            base: LlmOptions {
+                system_message: Some("Be brief.".to_string()),
+                ..LlmOptions::default()
+            },
+            sampling: SamplingOptions {
+                max_tokens: Some(512),
+                ..SamplingOptions::default()
+            },
+        };
+        let json: serde_json::Value = serde_json::from_slice(&options.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            json!({ "system_message": "Be brief.", "max_tokens": 512 })
+        );
+    }
+}
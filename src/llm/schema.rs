@@ -0,0 +1,409 @@
+use serde_json::{Map, Value};
+
+/// Most problems listed when asking the model to repair its reply
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// Find the JSON value in a model's reply. Models often wrap it in a code block or a
+/// sentence, so when the whole reply is not JSON the first object or array in it is taken.
+pub fn extract_json(reply: &str) -> Option<Value> {
+    if let Ok(value) = serde_json::from_str(reply.trim()) {
+        return Some(value);
+    }
+    reply.match_indices(['{', '[']).find_map(|(start, _)| {
+        serde_json::Deserializer::from_str(&reply[start..])
+            .into_iter::<Value>()
+            .next()
+            .and_then(Result::ok)
+    })
+}
+
+/// Keywords `validate` checks
+const CHECKED_KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "allOf",
+    "anyOf",
+    "oneOf",
+];
+
+/// Keywords that describe a schema without constraining values
+const ANNOTATION_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+];
+
+/// Check that a schema only uses keywords `validate` supports, so that no part of it is
+/// silently left unchecked. Nested schemas are checked too.
+pub fn check_supported(schema: &Value) -> Result<(), String> {
+    supported(schema, "")
+}
+
+fn supported(schema: &Value, path: &str) -> Result<(), String> {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        Value::Bool(_) => return Ok(()),
+        _ => {
+            return Err(format!(
+                "{}: a schema must be an object or a boolean",
+                at(path)
+            ))
+        }
+    };
+    for (name, value) in schema {
+        if ANNOTATION_KEYWORDS.contains(&name.as_str()) {
+            continue;
+        }
+        if !CHECKED_KEYWORDS.contains(&name.as_str()) {
+            return Err(format!(
+                "{}: the '{}' keyword is not supported",
+                at(path),
+                name
+            ));
+        }
+        let path = format!("{}/{}", path, escape(name));
+        match (name.as_str(), value) {
+            ("properties", Value::Object(properties)) => {
+                for (property, schema) in properties {
+                    supported(schema, &format!("{}/{}", path, escape(property)))?;
+                }
+            }
+            ("additionalProperties", schema) => supported(schema, &path)?,
+            ("items" | "allOf" | "anyOf" | "oneOf", Value::Array(schemas)) => {
+                for (i, schema) in schemas.iter().enumerate() {
+                    supported(schema, &format!("{}/{}", path, i))?;
+                }
+            }
+            ("items", schema) => supported(schema, &path)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check a value against a JSON Schema, returning where it does not conform.
+///
+/// Covers the keywords structured output schemas are made of: `type`, `enum`, `const`,
+/// `properties`, `required`, `additionalProperties`, `items`, `minItems`, `maxItems`,
+/// `minLength`, `maxLength`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`,
+/// `allOf`, `anyOf` and `oneOf`. Schemas using other keywords, such as `pattern` or `$ref`,
+/// are rejected beforehand by `check_supported`.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(schema, value, "", &mut errors);
+    errors.truncate(MAX_REPORTED_ERRORS);
+    errors
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        Value::Bool(false) => {
+            errors.push(format!("{}: no value is allowed", at(path)));
+            return;
+        }
+        _ => return,
+    };
+
+    let types = match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+        errors.push(format!(
+            "{}: expected {}, got {}",
+            at(path),
+            types.join(" or "),
+            type_name(value)
+        ));
+        return;
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!(
+                "{}: expected one of {}",
+                at(path),
+                Value::Array(allowed.clone())
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{}: expected {}", at(path), expected));
+        }
+    }
+
+    match value {
+        Value::Object(object) => check_object(schema, object, path, errors),
+        Value::Array(items) => check_array(schema, items, path, errors),
+        Value::String(string) => {
+            let length = string.chars().count() as f64;
+            if keyword(schema, "minLength").is_some_and(|min| length < min) {
+                errors.push(format!("{}: string is too short", at(path)));
+            }
+            if keyword(schema, "maxLength").is_some_and(|max| length > max) {
+                errors.push(format!("{}: string is too long", at(path)));
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            let below = keyword(schema, "minimum").is_some_and(|min| number < min)
+                || keyword(schema, "exclusiveMinimum").is_some_and(|min| number <= min);
+            let above = keyword(schema, "maximum").is_some_and(|max| number > max)
+                || keyword(schema, "exclusiveMaximum").is_some_and(|max| number >= max);
+            if below {
+                errors.push(format!("{}: {} is too small", at(path), number));
+            }
+            if above {
+                errors.push(format!("{}: {} is too large", at(path), number));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for schema in schemas {
+            check(schema, value, path, errors);
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+        if !schemas.iter().any(|schema| conforms(schema, value)) {
+            errors.push(format!(
+                "{}: does not match any of the allowed schemas",
+                at(path)
+            ));
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+        let matches = schemas
+            .iter()
+            .filter(|schema| conforms(schema, value))
+            .count();
+        if matches != 1 {
+            errors.push(format!(
+                "{}: must match exactly one of the allowed schemas, matches {}",
+                at(path),
+                matches
+            ));
+        }
+    }
+}
+
+fn check_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                errors.push(format!(
+                    "{}: missing required property '{}'",
+                    at(path),
+                    name
+                ));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let additional = schema.get("additionalProperties");
+    for (name, property) in object {
+        let property_path = format!("{}/{}", path, escape(name));
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property_schema) => check(property_schema, property, &property_path, errors),
+            None => match additional {
+                Some(Value::Bool(false)) => {
+                    errors.push(format!("{}: unexpected property", property_path))
+                }
+                Some(additional) => check(additional, property, &property_path, errors),
+                None => {}
+            },
+        }
+    }
+}
+
+fn check_array(schema: &Map<String, Value>, items: &[Value], path: &str, errors: &mut Vec<String>) {
+    let count = items.len() as f64;
+    if keyword(schema, "minItems").is_some_and(|min| count < min) {
+        errors.push(format!("{}: too few items", at(path)));
+    }
+    if keyword(schema, "maxItems").is_some_and(|max| count > max) {
+        errors.push(format!("{}: too many items", at(path)));
+    }
+    match schema.get("items") {
+        // A schema for each position, as older drafts write tuples
+        Some(Value::Array(schemas)) => {
+            for (i, (schema, item)) in schemas.iter().zip(items).enumerate() {
+                check(schema, item, &format!("{}/{}", path, i), errors);
+            }
+        }
+        Some(schema) => {
+            for (i, item) in items.iter().enumerate() {
+                check(schema, item, &format!("{}/{}", path, i), errors);
+            }
+        }
+        None => {}
+    }
+}
+
+fn conforms(schema: &Value, value: &Value) -> bool {
+    let mut errors = Vec::new();
+    check(schema, value, "", &mut errors);
+    errors.is_empty()
+}
+
+fn keyword(schema: &Map<String, Value>, name: &str) -> Option<f64> {
+    schema.get(name).and_then(Value::as_f64)
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        name => type_name(value) == name,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// A JSON Pointer (RFC 6901) reference token
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+fn at(path: &str) -> &str {
+    if path.is_empty() {
+        "value"
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn extracts_json_from_a_reply() {
+        assert_eq!(extract_json(" {\"a\": 1}\n"), Some(json!({ "a": 1 })));
+        assert_eq!(
+            extract_json("Sure! Here it is:\n```json\n[1, 2]\n```"),
+            Some(json!([1, 2]))
+        );
+        assert_eq!(
+            extract_json("Use {braces} like {\"a\": true} this"),
+            Some(json!({ "a": true }))
+        );
+        assert_eq!(extract_json("no json here"), None);
+    }
+
+    #[test]
+    fn validates_values() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "age": { "type": "integer", "minimum": 0 },
+                "tags": { "type": "array", "items": { "enum": ["a", "b"] }, "maxItems": 2 }
+            },
+            "required": ["name"],
+            "additionalProperties": false
+        });
+        assert!(validate(&schema, &json!({ "name": "Ann", "age": 3, "tags": ["a"] })).is_empty());
+        assert_eq!(
+            validate(&schema, &json!({ "age": 1.5, "tags": ["c"], "x/y": 1 })),
+            vec![
+                "value: missing required property 'name'",
+                "/age: expected integer, got number",
+                "/tags/0: expected one of [\"a\",\"b\"]",
+                "/x~1y: unexpected property",
+            ]
+        );
+        assert_eq!(
+            validate(&schema, &json!([])),
+            vec!["value: expected object, got array"]
+        );
+    }
+
+    #[test]
+    fn validates_combinators() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "minimum": 0 }] });
+        assert!(validate(&schema, &json!(-1)).is_empty());
+        assert_eq!(
+            validate(&schema, &json!(1)),
+            vec!["value: must match exactly one of the allowed schemas, matches 2"]
+        );
+        let schema = json!({ "anyOf": [{ "type": "string" }, { "type": "null" }] });
+        assert!(validate(&schema, &json!(null)).is_empty());
+        assert_eq!(validate(&schema, &json!(1)).len(), 1);
+    }
+
+    #[test]
+    fn rejects_unsupported_keywords() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Person",
+            "type": "object",
+            "properties": { "name": { "type": "string", "description": "Full name" } },
+            "items": [true, { "type": "number" }]
+        });
+        assert_eq!(check_supported(&schema), Ok(()));
+
+        for (schema, error) in [
+            (
+                json!({ "$ref": "#/$defs/a", "$defs": { "a": {} } }),
+                "value: the '$defs' keyword is not supported",
+            ),
+            (
+                json!({ "properties": { "id": { "type": "string", "format": "uuid" } } }),
+                "/properties/id: the 'format' keyword is not supported",
+            ),
+            (
+                json!({ "anyOf": [{ "type": "string" }, { "pattern": "^a" }] }),
+                "/anyOf/1: the 'pattern' keyword is not supported",
+            ),
+            (
+                json!({ "items": { "not": { "type": "null" } } }),
+                "/items: the 'not' keyword is not supported",
+            ),
+            (
+                json!({ "additionalProperties": 1 }),
+                "/additionalProperties: a schema must be an object or a boolean",
+            ),
+        ] {
+            assert_eq!(check_supported(&schema), Err(error.to_string()));
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use anyhow::{anyhow, bail, Result};
+use blockless_sdk::llm::{BlocklessLlm, LlmErrorKind, LlmOptions, Models};
+
+use super::{
+    options::{ChatOptions, SamplingOptions},
+    schema,
+};
+
+// The host functions behind the SDK's `set_options` and `get_options`. The SDK serializes and
+// parses only the options it knows, so they are called directly to carry the sampling
+// parameters as well, under the same contract the SDK relies on:
+// - `llm_set_model_options_request` takes the options of a handle as a UTF-8 JSON object of at
+//   most `u16::MAX` bytes, and returns 0 or an `LlmErrorKind` code
+// - `llm_get_model_options` writes the options the handle uses, as a JSON object, into a buffer
+//   of `buf_len` bytes, sets `bytes_written`, and returns 0 or an `LlmErrorKind` code
+// Hosts that do not support a sampling parameter leave it out of the options they report.
+// The SDK keeps its own declarations private; these mirror those of blockless-sdk 0.2.3
+// (`src/llm.rs`, where `Handle` is `u32` and `ExitCode` is `u8`) and must follow them when the
+// SDK is upgraded.
+#[link(wasm_import_module = "blockless_llm")]
+extern "C" {
+    fn llm_set_model_options_request(h: u32, options_ptr: *const u8, options_len: u16) -> u8;
+    fn llm_get_model_options(h: u32, buf: *mut u8, buf_len: u16, bytes_written: *mut u16) -> u8;
+}
+
+/// Longest prompt the host takes, whose length is passed as a `u16`
+const MAX_PROMPT_LEN: usize = u16::MAX as usize;
+
+/// Who wrote a message of a conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "system" => Some(Self::System),
+            "user" => Some(Self::User),
+            "assistant" => Some(Self::Assistant),
+            "tool" => Some(Self::Tool),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::Tool => "tool",
+        }
+    }
+}
+
+/// A message of a conversation, as passed to `chatMessages` and returned by `history`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+    /// The tool that produced a `tool` message, or the name of the participant
+    pub name: Option<String>,
+    /// The tool call a `tool` message answers
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            name: None,
+            tool_call_id: None,
+        }
+    }
+}
+
+/// A conversation with a model.
+///
+/// The host keeps the conversation of each model handle to itself, with no way to inspect
+/// or rewind it. The session records the messages exchanged, and starting over (on `reset`,
+/// or to replay a given history) opens a new handle with the same model and options.
+pub struct ChatSession {
+    model: Models,
+    llm: BlocklessLlm,
+    /// Options set by the script, applied again to every new handle
+    options: ChatOptions,
+    history: Vec<ChatMessage>,
+}
+
+impl ChatSession {
+    pub fn new(model: Models) -> Result<Self> {
+        let llm = open(&model)?;
+        Ok(Self {
+            model,
+            llm,
+            options: ChatOptions::default(),
+            history: Vec::new(),
+        })
+    }
+
+    /// The options of the current handle, sampling parameters included, as the host reports them
+    pub fn options(&self) -> Result<ChatOptions> {
+        host_options(&self.llm).map_err(|e| anyhow!("Get options failed: {:?}", e))
+    }
+
+    pub fn set_options(&mut self, options: ChatOptions) -> Result<()> {
+        apply_options(&self.llm, &options)?;
+        self.options = options;
+        Ok(())
+    }
+
+    /// Send a prompt, continuing the conversation
+    pub fn chat(&mut self, prompt: &str) -> Result<String> {
+        let reply = self.request(prompt)?;
+        self.record(prompt, &reply);
+        Ok(reply)
+    }
+
+    /// Start a new conversation from the given messages and return the assistant's reply.
+    /// System messages replace the configured system message for this conversation. The host
+    /// takes one prompt at a time, so the other messages are replayed to the model as a single
+    /// transcript prompt ending with the last user or tool message, which must fit in
+    /// `MAX_PROMPT_LEN` bytes.
+    pub fn chat_messages(&mut self, messages: Vec<ChatMessage>) -> Result<ChatMessage> {
+        let (prompt, system_message) = replay(&messages)?;
+        let mut options = self.options.clone();
+        if system_message.is_some() {
+            options.base.system_message = system_message;
+        }
+        self.restart(&options)?;
+        self.history.clear();
+
+        let reply = ChatMessage::new(Role::Assistant, self.request(&prompt)?);
+        self.history = messages;
+        self.history.push(reply.clone());
+        Ok(reply)
+    }
+
+    /// Ask for a JSON value conforming to a JSON Schema. Replies that contain no JSON, or JSON
+    /// that does not conform, are answered with a prompt listing the problems, up to `retries` times.
+    ///
+    /// Only the first prompt and the valid reply are recorded in the history, and nothing when
+    /// every attempt fails. The host keeps its own conversation for the handle, so the rejected
+    /// replies and repair prompts still precede later prompts there.
+    pub fn chat_json(
+        &mut self,
+        prompt: &str,
+        schema: &serde_json::Value,
+        retries: u32,
+    ) -> Result<serde_json::Value> {
+        let instructions = format!(
+            "Reply with a single JSON value that conforms to the following JSON Schema, \
+             without any other text or formatting:\n{}",
+            schema
+        );
+        let first_request = format!("{}\n\n{}", prompt, instructions);
+        if first_request.len() > MAX_PROMPT_LEN {
+            bail!(
+                "chatJSON failed: the prompt and schema are {} bytes, the host accepts at most {}",
+                first_request.len(),
+                MAX_PROMPT_LEN
+            );
+        }
+
+        let mut request = first_request.clone();
+        let mut problems = String::new();
+        for _ in 0..=retries {
+            let reply = self.request(&request)?;
+            problems = match schema::extract_json(&reply) {
+                Some(value) => {
+                    let errors = schema::validate(schema, &value);
+                    if errors.is_empty() {
+                        self.record(&first_request, &reply);
+                        return Ok(value);
+                    }
+                    errors.join("; ")
+                }
+                None => "it does not contain JSON".to_string(),
+            };
+            request = format!("Your reply is not valid: {}.\n\n{}", problems, instructions);
+        }
+        bail!(
+            "chatJSON failed: no valid reply after {} attempts, the last one is not valid: {}",
+            retries + 1,
+            problems
+        )
+    }
+
+    /// The messages exchanged since the conversation started
+    pub fn history(&self) -> &[ChatMessage] {
+        &self.history
+    }
+
+    /// Forget the conversation, later prompts start a new one with the configured options
+    pub fn reset(&mut self) -> Result<()> {
+        let options = self.options.clone();
+        self.restart(&options)?;
+        self.history.clear();
+        Ok(())
+    }
+
+    fn request(&self, prompt: &str) -> Result<String> {
+        check_prompt_len(prompt)?;
+        self.llm
+            .chat_request(prompt)
+            .map_err(|e| anyhow!("Chat request failed: {:?}", e))
+    }
+
+    /// Add an exchange to the history, starting it with the configured system message
+    fn record(&mut self, prompt: &str, reply: &str) {
+        if self.history.is_empty() {
+            if let Some(system_message) = &self.options.base.system_message {
+                self.history
+                    .push(ChatMessage::new(Role::System, system_message.clone()));
+            }
+        }
+        self.history.push(ChatMessage::new(Role::User, prompt));
+        self.history.push(ChatMessage::new(Role::Assistant, reply));
+    }
+
+    /// Replace the handle with a new one, closing the old handle and its conversation
+    fn restart(&mut self, options: &ChatOptions) -> Result<()> {
+        let llm = open(&self.model)?;
+        if *options != ChatOptions::default() {
+            apply_options(&llm, options)?;
+        }
+        self.llm = llm;
+        Ok(())
+    }
+}
+
+/// Set the options of a handle, checking that the host took those it reports back
+fn apply_options(llm: &BlocklessLlm, options: &ChatOptions) -> Result<()> {
+    let options_json = options.to_json()?;
+    let options_len = u16::try_from(options_json.len())
+        .map_err(|_| anyhow!("Set options failed: the options exceed {} bytes", u16::MAX))?;
+    // SAFETY: the pointer and length describe `options_json`, which outlives the call, and the
+    // host only reads that many bytes from it. `llm.handle()` is the handle of an open model.
+    let code =
+        unsafe { llm_set_model_options_request(llm.handle(), options_json.as_ptr(), options_len) };
+    if code != 0 {
+        return Err(anyhow!(
+            "Set options failed: {:?}",
+            LlmErrorKind::from(code)
+        ));
+    }
+
+    let host_options = host_options(llm).map_err(|e| anyhow!("Set options failed: {:?}", e))?;
+    if host_options.base != options.base {
+        return Err(anyhow!(
+            "Set options failed: {:?}",
+            LlmErrorKind::ModelOptionsNotSet
+        ));
+    }
+    let not_applied = options.sampling.not_applied(&host_options.sampling);
+    if !not_applied.is_empty() {
+        bail!(
+            "Set options failed: the host did not apply {}",
+            not_applied.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Read the options of a handle. The sampling parameters the host does not report, or reports
+/// in a shape other than the one they were set in, are left out.
+fn host_options(llm: &BlocklessLlm) -> Result<ChatOptions, LlmErrorKind> {
+    let mut buf = vec![0u8; u16::MAX as usize];
+    let mut num_bytes: u16 = 0;
+    // SAFETY: `buf` has room for the `u16::MAX` bytes the host is allowed to write, and
+    // `num_bytes` is a live `u16` for it to store the written length in.
+    let code =
+        unsafe { llm_get_model_options(llm.handle(), buf.as_mut_ptr(), u16::MAX, &mut num_bytes) };
+    if code != 0 {
+        return Err(LlmErrorKind::from(code));
+    }
+    buf.truncate(num_bytes.into());
+
+    let reported = serde_json::from_slice(&buf).unwrap_or_default();
+    let sampling = SamplingOptions::from_host(&reported);
+    Ok(ChatOptions {
+        base: LlmOptions::try_from(buf)?,
+        sampling,
+    })
+}
+
+fn check_prompt_len(prompt: &str) -> Result<()> {
+    if prompt.len() > MAX_PROMPT_LEN {
+        bail!(
+            "Chat request failed: the prompt is {} bytes, the host accepts at most {}",
+            prompt.len(),
+            MAX_PROMPT_LEN
+        );
+    }
+    Ok(())
+}
+
+fn open(model: &Models) -> Result<BlocklessLlm> {
+    BlocklessLlm::new(model.clone()).map_err(|e| anyhow!("Failed to load model {}: {:?}", model, e))
+}
+
+/// The prompt and system message a conversation is replayed with. The conversation must end
+/// with a message for the assistant to answer, and its system messages are joined into one.
+fn replay(messages: &[ChatMessage]) -> Result<(String, Option<String>)> {
+    match messages.last().map(|message| message.role) {
+        None => bail!("messages must not be empty"),
+        Some(Role::User | Role::Tool) => {}
+        Some(role) => bail!(
+            "The last message must be a 'user' or 'tool' message, got '{}'",
+            role.as_str()
+        ),
+    }
+
+    let turns = messages
+        .iter()
+        .filter(|message| message.role != Role::System)
+        .collect::<Vec<_>>();
+    let prompt = transcript(&turns);
+    check_prompt_len(&prompt)?;
+
+    let system_message = messages
+        .iter()
+        .filter(|message| message.role == Role::System)
+        .map(|message| message.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok((
+        prompt,
+        (!system_message.is_empty()).then_some(system_message),
+    ))
+}
+
+/// Render the messages of a conversation as a single prompt. A lone user message is sent as is.
+fn transcript(messages: &[&ChatMessage]) -> String {
+    if let [message] = messages {
+        if message.role == Role::User {
+            return message.content.clone();
+        }
+    }
+
+    let mut prompt = String::from(
+        "Continue the conversation below. Reply with the assistant's next message only.\n",
+    );
+    for message in messages {
+        let speaker = match (message.role, &message.name) {
+            (Role::User, _) => "User".to_string(),
+            (Role::Assistant, _) => "Assistant".to_string(),
+            (Role::Tool, Some(name)) => format!("Tool result ({})", name),
+            (Role::Tool, None) => "Tool result".to_string(),
+            (Role::System, _) => continue,
+        };
+        prompt.push_str(&format!("\n{}: {}\n", speaker, message.content));
+    }
+    prompt.push_str("\nAssistant:");
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: Role, content: &str) -> ChatMessage {
+        ChatMessage::new(role, content)
+    }
+
+    #[test]
+    fn a_lone_user_message_is_sent_as_is() {
+        let (prompt, system_message) = replay(&[message(Role::User, "Hi!")]).unwrap();
+        assert_eq!(prompt, "Hi!");
+        assert_eq!(system_message, None);
+    }
+
+    #[test]
+    fn conversations_are_replayed_as_a_transcript() {
+        let mut tool = message(Role::Tool, "22C");
+        tool.name = Some("weather".to_string());
+        let messages = [
+            message(Role::System, "Be brief."),
+            message(Role::User, "Weather in Paris?"),
+            message(Role::Assistant, "Let me check."),
+            message(Role::System, "Use Celsius."),
+            tool,
+        ];
+        let (prompt, system_message) = replay(&messages).unwrap();
+        assert_eq!(
+            prompt,
+            "Continue the conversation below. Reply with the assistant's next message only.\n\
+             \nUser: Weather in Paris?\n\
+             \nAssistant: Let me check.\n\
+             \nTool result (weather): 22C\n\
+             \nAssistant:"
+        );
+        assert_eq!(system_message.as_deref(), Some("Be brief.\n\nUse Celsius."));
+    }
+
+    #[test]
+    fn a_lone_tool_message_is_not_sent_as_is() {
+        let (prompt, _) = replay(&[message(Role::Tool, "42")]).unwrap();
+        assert!(prompt.ends_with("\nTool result: 42\n\nAssistant:"));
+    }
+
+    #[test]
+    fn conversations_must_end_with_a_message_to_answer() {
+        let error = replay(&[]).unwrap_err();
+        assert_eq!(error.to_string(), "messages must not be empty");
+        for role in [Role::Assistant, Role::System] {
+            let messages = [message(Role::User, "Hi!"), message(role, "Hello.")];
+            assert_eq!(
+                replay(&messages).unwrap_err().to_string(),
+                format!(
+                    "The last message must be a 'user' or 'tool' message, got '{}'",
+                    role.as_str()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn transcripts_longer_than_the_host_accepts_fail() {
+        let messages = [message(Role::User, &"a".repeat(MAX_PROMPT_LEN + 1))];
+        assert!(replay(&messages).is_err());
+        let messages = [message(Role::User, &"a".repeat(MAX_PROMPT_LEN))];
+        assert!(replay(&messages).is_ok());
+    }
+}
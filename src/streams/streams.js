@@ -0,0 +1,165 @@
+// Wrap everything in an anonymous function to avoid leaking local variables into the global scope.
+(function () {
+    // Minimal pull-based ReadableStream, shared by fetch (response bodies, Blob.stream()) and
+    // the compression streams. A ReadableStream provided by the host is kept.
+    class ReadableStream {
+        constructor(underlyingSource = {}) {
+            this._source = underlyingSource;
+            this._queue = [];
+            this._closed = false;
+            this._errored = false;
+            this._error = undefined;
+            this._locked = false;
+            this._started = false;
+            // Set once reading or cancelling started, a disturbed body counts as used
+            this._disturbed = false;
+
+            const controller = {
+                enqueue: (chunk) => { this._queue.push(chunk); },
+                close: () => { this._closed = true; },
+                error: (e) => { this._errored = true; this._error = e; },
+            };
+            this._controller = controller;
+            if (this._source.start) {
+                this._startPromise = Promise.resolve(this._source.start(controller));
+            } else {
+                this._startPromise = Promise.resolve();
+            }
+        }
+
+        get locked() { return this._locked; }
+
+        async _read() {
+            this._disturbed = true;
+            await this._startPromise;
+            while (this._queue.length === 0 && !this._closed && !this._errored) {
+                if (!this._source.pull) {
+                    break;
+                }
+                try {
+                    await this._source.pull(this._controller);
+                } catch (e) {
+                    this._controller.error(e);
+                }
+            }
+            if (this._errored) {
+                throw this._error;
+            }
+            if (this._queue.length > 0) {
+                return { value: this._queue.shift(), done: false };
+            }
+            return { value: undefined, done: true };
+        }
+
+        async cancel(reason) {
+            this._disturbed = true;
+            this._queue = [];
+            this._closed = true;
+            if (this._source.cancel) {
+                await this._source.cancel(reason);
+            }
+        }
+
+        getReader() {
+            if (this._locked) {
+                throw new TypeError('ReadableStream is locked');
+            }
+            this._locked = true;
+            let released = false;
+            return {
+                read: () => released
+                    ? Promise.reject(new TypeError('Reader has been released'))
+                    : this._read(),
+                releaseLock: () => {
+                    released = true;
+                    this._locked = false;
+                },
+                cancel: (reason) => this.cancel(reason),
+                get closed() { return Promise.resolve(); },
+            };
+        }
+
+        // Split the stream into two branches that each see every chunk.
+        // Chunks are buffered until both branches read them.
+        tee() {
+            const reader = this.getReader();
+            const buffers = [[], []];
+            let done = false;
+            let cancelled = 0;
+            const branch = (index) => new ReadableStream({
+                async pull(controller) {
+                    if (buffers[index].length === 0 && !done) {
+                        const result = await reader.read();
+                        if (result.done) {
+                            done = true;
+                        } else {
+                            buffers[0].push(result.value);
+                            buffers[1].push(result.value);
+                        }
+                    }
+                    if (buffers[index].length > 0) {
+                        controller.enqueue(buffers[index].shift());
+                    } else if (done) {
+                        controller.close();
+                    }
+                },
+                async cancel(reason) {
+                    buffers[index] = [];
+                    // The source is cancelled once neither branch needs it
+                    if (++cancelled === 2) {
+                        await reader.cancel(reason);
+                    }
+                },
+            });
+            return [branch(0), branch(1)];
+        }
+
+        // Write every chunk to a writable stream, closing it at the end
+        // or aborting it if reading fails.
+        async pipeTo(destination, options = {}) {
+            const reader = this.getReader();
+            const writer = destination.getWriter();
+            try {
+                while (true) {
+                    const { value, done } = await reader.read();
+                    if (done) {
+                        break;
+                    }
+                    await writer.write(value);
+                }
+                if (!options.preventClose) {
+                    await writer.close();
+                }
+            } catch (e) {
+                if (!options.preventAbort) {
+                    await writer.abort(e);
+                }
+                throw e;
+            } finally {
+                reader.releaseLock();
+                writer.releaseLock();
+            }
+        }
+
+        // Pipe through a transform such as a CompressionStream, returning its readable side.
+        // Errors reach the consumer through the readable side.
+        pipeThrough(transform, options) {
+            this.pipeTo(transform.writable, options).catch(() => {});
+            return transform.readable;
+        }
+
+        [Symbol.asyncIterator]() {
+            const reader = this.getReader();
+            return {
+                next: () => reader.read(),
+                return: async (value) => {
+                    reader.releaseLock();
+                    return { value, done: true };
+                },
+                [Symbol.asyncIterator]() { return this; },
+            };
+        }
+    }
+
+    globalThis.ReadableStream = globalThis.ReadableStream || ReadableStream;
+})();
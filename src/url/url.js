@@ -0,0 +1,237 @@
+// Wrap everything in an anonymous function to avoid leaking local variables into the global scope.
+(function () {
+    // Get a reference to the functions before we delete them from `globalThis`.
+    const __javy_bless_url_parse = globalThis.__javy_bless_url_parse;
+    const __javy_bless_url_get = globalThis.__javy_bless_url_get;
+    const __javy_bless_url_set = globalThis.__javy_bless_url_set;
+    const __javy_bless_url_search_params_parse = globalThis.__javy_bless_url_search_params_parse;
+    const __javy_bless_url_search_params_serialize = globalThis.__javy_bless_url_search_params_serialize;
+
+    function parseQuery(query) {
+        query = String(query);
+        if (query.startsWith('?')) {
+            query = query.slice(1);
+        }
+        return __javy_bless_url_search_params_parse(query);
+    }
+
+    class URLSearchParams {
+        constructor(init = '') {
+            this._list = [];
+            // The URL these params belong to, if any
+            this._url = null;
+
+            if (init instanceof URLSearchParams) {
+                this._list = init._list.map(([name, value]) => [name, value]);
+            } else if (init !== null && typeof init === 'object' && typeof init[Symbol.iterator] === 'function') {
+                for (const pair of init) {
+                    const entry = Array.from(pair);
+                    if (entry.length !== 2) {
+                        throw new TypeError("Failed to construct 'URLSearchParams': Each query pair must be an iterable [name, value] tuple");
+                    }
+                    this._list.push([String(entry[0]), String(entry[1])]);
+                }
+            } else if (init !== null && typeof init === 'object') {
+                for (const key of Object.keys(init)) {
+                    this._list.push([key, String(init[key])]);
+                }
+            } else {
+                this._list = parseQuery(init);
+            }
+        }
+
+        // Write the params back to the owning URL's query
+        _update() {
+            if (this._url) {
+                __javy_bless_url_set(this._url._handle, 'search', this.toString());
+            }
+        }
+
+        get size() { return this._list.length; }
+
+        append(name, value) {
+            this._list.push([String(name), String(value)]);
+            this._update();
+        }
+
+        delete(name, value) {
+            name = String(name);
+            this._list = value === undefined
+                ? this._list.filter(entry => entry[0] !== name)
+                : this._list.filter(entry => entry[0] !== name || entry[1] !== String(value));
+            this._update();
+        }
+
+        get(name) {
+            name = String(name);
+            const entry = this._list.find(entry => entry[0] === name);
+            return entry ? entry[1] : null;
+        }
+
+        getAll(name) {
+            name = String(name);
+            return this._list.filter(entry => entry[0] === name).map(entry => entry[1]);
+        }
+
+        has(name, value) {
+            name = String(name);
+            return value === undefined
+                ? this._list.some(entry => entry[0] === name)
+                : this._list.some(entry => entry[0] === name && entry[1] === String(value));
+        }
+
+        set(name, value) {
+            name = String(name);
+            const entry = [name, String(value)];
+            const index = this._list.findIndex(entry => entry[0] === name);
+            if (index === -1) {
+                this._list.push(entry);
+            } else {
+                // Replace the first entry in place and drop the rest
+                this._list = this._list.filter((entry, i) => i <= index || entry[0] !== name);
+                this._list[index] = entry;
+            }
+            this._update();
+        }
+
+        sort() {
+            // Array.prototype.sort is stable and compares UTF-16 code units
+            this._list.sort((a, b) => (a[0] < b[0] ? -1 : a[0] > b[0] ? 1 : 0));
+            this._update();
+        }
+
+        toString() {
+            return __javy_bless_url_search_params_serialize(this._list);
+        }
+
+        *entries() {
+            for (const [name, value] of this._list) {
+                yield [name, value];
+            }
+        }
+
+        *keys() {
+            for (const [name] of this._list) {
+                yield name;
+            }
+        }
+
+        *values() {
+            for (const [, value] of this._list) {
+                yield value;
+            }
+        }
+
+        forEach(callback, thisArg) {
+            for (const [name, value] of this.entries()) {
+                callback.call(thisArg, value, name, this);
+            }
+        }
+
+        [Symbol.iterator]() {
+            return this.entries();
+        }
+
+        get [Symbol.toStringTag]() { return 'URLSearchParams'; }
+    }
+
+    class URL {
+        constructor(url, base) {
+            url = String(url);
+            const handle = __javy_bless_url_parse(url, base === undefined ? undefined : String(base));
+            if (handle === null) {
+                throw new TypeError(`Failed to construct 'URL': Invalid URL: ${url}`);
+            }
+            this._handle = handle;
+            this._searchParams = new URLSearchParams(this.search);
+            this._searchParams._url = this;
+        }
+
+        static canParse(url, base) {
+            return __javy_bless_url_parse(String(url), base === undefined ? undefined : String(base)) !== null;
+        }
+
+        static parse(url, base) {
+            try {
+                return new URL(url, base);
+            } catch {
+                return null;
+            }
+        }
+
+        _get(component) {
+            return __javy_bless_url_get(this._handle, component);
+        }
+
+        _set(component, value) {
+            return __javy_bless_url_set(this._handle, component, String(value));
+        }
+
+        // Keep searchParams in sync after the query changed
+        _refreshSearchParams() {
+            this._searchParams._list = parseQuery(this.search);
+        }
+
+        get href() { return this._get('href'); }
+        set href(value) {
+            if (!this._set('href', value)) {
+                throw new TypeError(`Failed to set the 'href' property on 'URL': Invalid URL: ${value}`);
+            }
+            this._refreshSearchParams();
+        }
+
+        get origin() { return this._get('origin'); }
+
+        get protocol() { return this._get('protocol'); }
+        set protocol(value) { this._set('protocol', value); }
+
+        get username() { return this._get('username'); }
+        set username(value) { this._set('username', value); }
+
+        get password() { return this._get('password'); }
+        set password(value) { this._set('password', value); }
+
+        get host() { return this._get('host'); }
+        set host(value) { this._set('host', value); }
+
+        get hostname() { return this._get('hostname'); }
+        set hostname(value) { this._set('hostname', value); }
+
+        get port() { return this._get('port'); }
+        set port(value) { this._set('port', value); }
+
+        get pathname() { return this._get('pathname'); }
+        set pathname(value) { this._set('pathname', value); }
+
+        get search() { return this._get('search'); }
+        set search(value) {
+            this._set('search', value);
+            this._refreshSearchParams();
+        }
+
+        get searchParams() { return this._searchParams; }
+
+        get hash() { return this._get('hash'); }
+        set hash(value) { this._set('hash', value); }
+
+        toString() {
+            return this.href;
+        }
+
+        toJSON() {
+            return this.href;
+        }
+
+        get [Symbol.toStringTag]() { return 'URL'; }
+    }
+
+    globalThis.URL = URL;
+    globalThis.URLSearchParams = URLSearchParams;
+
+    // Delete the functions from `globalThis` so they don't leak.
+    Reflect.deleteProperty(globalThis, "__javy_bless_url_parse");
+    Reflect.deleteProperty(globalThis, "__javy_bless_url_get");
+    Reflect.deleteProperty(globalThis, "__javy_bless_url_set");
+    Reflect.deleteProperty(globalThis, "__javy_bless_url_search_params_parse");
+    Reflect.deleteProperty(globalThis, "__javy_bless_url_search_params_serialize");
+})();